@@ -1,29 +1,47 @@
-// extern crate rustc_serialize;
-// extern crate sofa_serialize;
-//
-// use std::env;
-// use std::fs;
-// use std::io;
-// use std::io::{Read};
-
-// use sofa_serialize::Serialize;
-
-// fn read_dictionary(name: &str) -> io::Result<Vec<String>> {
-//     let mut r = String::new();
-//     let mut f = try!(fs::File::open(name));
-//     try!(f.read_to_string(&mut r));
-//     return Ok(r.lines().map(|x| { x.to_string() }).collect());
-// }
+extern crate rustc_serialize;
+extern crate sofa_serialize;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::process;
+
+use rustc_serialize::Encodable;
+
+fn read_dictionary(name: &str) -> io::Result<Vec<String>> {
+    let mut r = String::new();
+    let mut f = try!(fs::File::open(name));
+    try!(f.read_to_string(&mut r));
+    return Ok(r.lines().map(|x| { x.to_string() }).collect());
+}
+
+fn run(name: &str) -> Result<(), Box<Error>> {
+    let dictionary = try!(read_dictionary(name));
+    let dictionary: Vec<&str> = dictionary.iter().map(|x| { &x[..] }).collect();
+
+    let (mut stdin, mut stdout) = (io::stdin(), io::stdout());
+    let json = try!(rustc_serialize::json::Json::from_reader(&mut stdin));
+
+    let mut encoder = sofa_serialize::Encoder::new(&mut stdout, &dictionary);
+
+    try!(json.encode(&mut encoder));
+    try!(encoder.finish());
+
+    return Ok(());
+}
 
 fn main() {
-    // let args: Vec<String> = env::args().collect();
-    //
-    // let dictionary = read_dictionary(&args[1]).unwrap();
-    //
-    // let (mut stdin, mut stdout) = (io::stdin(), io::stdout());
-    // let json = rustc_serialize::json::Json::from_reader(&mut stdin).unwrap();
-    //
-    // let mut encoder = sofa_serialize::Encoder::new(&mut stdout, dictionary);
-    //
-    // json.serialize(&mut encoder).unwrap();
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() != 2 {
+        let _ = writeln!(io::stderr(), "usage: sofa-serialize <dictionary>");
+        process::exit(2);
+    }
+
+    if let Err(e) = run(&args[1]) {
+        let _ = writeln!(io::stderr(), "sofa-serialize: {}", e);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,116 @@
+//! The canonical form of a sofa stream, where every value has exactly one encoding.
+//!
+//! `EncoderOptions::canonical` writes this form and `DecoderOptions::strict` rejects anything else
+//! with `NonCanonical`. A stream is canonical when
+//!
+//! * every container is sized, the encoder buffers streaming containers to count them;
+//! * every length and dictionary index uses its shortest header, and arrays, maps and strings use
+//!   the short `0x2N`, `0x3N` and `0x6N`/`0x7N` forms where they fit;
+//! * integers and floats use the narrowest type of the same kind that holds the value exactly, so
+//!   `-0.0` stays `-0.0` as an `f32` and every NaN becomes the `f32` NaN `0x7FC00000`;
+//! * fixnums have no redundant sign extension bytes;
+//! * the keys of a map or an open struct are in strictly ascending order of their encoded bytes,
+//!   and a repeated key is rejected with `DuplicateKey`.
+//!
+//! Values given to `Encoder::write_raw` are checked with the strict decoder.
+
+use super::Event;
+
+use fixnum;
+
+const NAN: u32 = 0x7FC00000;
+
+pub fn compact<'a>(event: &Event<'a>) -> Event<'a> {
+    return match *event {
+        Event::U16(v) => compact_unsigned(v as u64),
+        Event::U32(v) => compact_unsigned(v as u64),
+        Event::U64(v) => compact_unsigned(v),
+        Event::I16(v) => compact_signed(v as i64),
+        Event::I32(v) => compact_signed(v as i64),
+        Event::I64(v) => compact_signed(v),
+        Event::F64(v) if (v as f32) as f64 == v => Event::F32(v as f32),
+        ref event => event.clone()
+    };
+}
+
+pub fn canonicalize<'a>(event: &Event<'a>) -> Event<'a> {
+    return match *event {
+        Event::F32(v) if v.is_nan() => Event::F32(f32::from_bits(NAN)),
+        Event::F64(v) if v.is_nan() => Event::F32(f32::from_bits(NAN)),
+        Event::Fixnum(v) => Event::Fixnum(fixnum::trim(v)),
+        ref event => compact(event)
+    };
+}
+
+pub fn is_canonical(event: &Event) -> bool {
+    return match *event {
+        Event::F32(v) => !v.is_nan() || v.to_bits() == NAN,
+        Event::F64(v) => !v.is_nan() && (v as f32) as f64 != v,
+        ref event => canonicalize(event) == *event
+    };
+}
+
+fn compact_unsigned<'a>(v: u64) -> Event<'a> {
+    if v <= u8::MAX as u64 {
+        return Event::U8(v as u8);
+    } else if v <= u16::MAX as u64 {
+        return Event::U16(v as u16);
+    } else if v <= u32::MAX as u64 {
+        return Event::U32(v as u32);
+    }
+
+    return Event::U64(v);
+}
+
+fn compact_signed<'a>(v: i64) -> Event<'a> {
+    if v >= i8::MIN as i64 && v <= i8::MAX as i64 {
+        return Event::I8(v as i8);
+    } else if v >= i16::MIN as i64 && v <= i16::MAX as i64 {
+        return Event::I16(v as i16);
+    } else if v >= i32::MIN as i64 && v <= i32::MAX as i64 {
+        return Event::I32(v as i32);
+    }
+
+    return Event::I64(v);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+
+    use ::{Event, Size};
+
+    use super::{canonicalize, is_canonical};
+
+    #[test]
+    fn canonicalizes_events() {
+        assert_eq!(canonicalize(&Event::U64(7)), Event::U8(7));
+        assert_eq!(canonicalize(&Event::I32(-300)), Event::I16(-300));
+        assert_eq!(canonicalize(&Event::F64(0.5)), Event::F32(0.5));
+        assert_eq!(canonicalize(&Event::Fixnum(&[0x01, 0x00, 0x00])), Event::Fixnum(&[0x01]));
+        assert_eq!(canonicalize(&Event::StartMap(Size::U64(2))), Event::StartMap(Size::U64(2)));
+
+        match canonicalize(&Event::F64(f64::NAN)) {
+            Event::F32(v) => assert_eq!(v.to_bits(), 0x7FC00000),
+            event => panic!("unexpected {:?}", event)
+        }
+    }
+
+    #[test]
+    fn recognizes_canonical_events() {
+        assert!(is_canonical(&Event::U8(7)));
+        assert!(is_canonical(&Event::U16(0x100)));
+        assert!(is_canonical(&Event::F64(0.1)));
+        assert!(is_canonical(&Event::F32(f32::from_bits(0x7FC00000))));
+        assert!(is_canonical(&Event::Fixnum(&[0xFF])));
+        assert!(is_canonical(&Event::String("a")));
+
+        assert!(!is_canonical(&Event::U16(7)));
+        assert!(!is_canonical(&Event::I64(-1)));
+        assert!(!is_canonical(&Event::F64(0.5)));
+        assert!(!is_canonical(&Event::F64(f64::NAN)));
+        assert!(!is_canonical(&Event::F32(f32::from_bits(0x7FC00001))));
+        assert!(!is_canonical(&Event::Fixnum(&[0x00])));
+        assert!(!is_canonical(&Event::Fixnum(&[0xFF, 0xFF])));
+    }
+}
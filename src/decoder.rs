@@ -1,12 +1,18 @@
 use std::cmp;
+use std::collections;
 use std::io;
 use std::str;
 
-use byteorder::{LittleEndian, ReadBytesExt};
-
 use super::{Event, Size};
 
+use canonical;
 use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use decoder_limits::DecoderLimits;
+use decoder_options::DecoderOptions;
+use path::Path;
+use stack::StackSize;
+use token;
+use visitor::Visitor;
 
 pub trait BorrowRead<'a> : io::Read {
     fn fill_buffer(&self) -> &'a [u8];
@@ -37,217 +43,785 @@ impl<'a> BorrowRead<'a> for io::Cursor<&'a [u8]> {
     }
 }
 
+struct Checkpoint<'a> {
+    buffer: &'a [u8],
+    depth: usize,
+    top: Option<StackSize>,
+    keys: usize,
+    key: Option<(bool, Option<&'a [u8]>)>
+}
+
 pub struct Decoder<'a> {
-    reader: &'a mut BorrowRead<'a>,
+    reader: Option<&'a mut BorrowRead<'a>>,
+    buffer: &'a [u8],
     dictionary: &'a [&'a str],
-    stack: Vec<usize>
+    stack: Vec<StackSize>,
+    path: Path<&'a str>,
+    options: DecoderOptions,
+    start: usize,
+    failed: bool,
+    indices: collections::HashMap<&'a str, usize>,
+    keys: Vec<(bool, Option<&'a [u8]>)>,
+    // The unit variant being decoded by `rustc_serialize`, which has no arguments to read.
+    pub(crate) unit_variant: Option<&'a str>
+}
+
+macro_rules! expect_integer {
+    ($name:ident, $ty:ty, $expected:expr, $($variant:ident),*) => {
+        pub fn $name(&mut self) -> DecoderResult<$ty> {
+            return self.expect($expected, |event| match *event {
+                $(Event::$variant(v) => Some(<$ty>::from(v)),)*
+                _ => None
+            });
+        }
+    }
 }
 
 impl<'a> Decoder<'a> {
     pub fn new(reader: &'a mut BorrowRead<'a>, dictionary: &'a [&'a str]) -> Decoder<'a> {
-        return Decoder { reader: reader, dictionary: dictionary, stack: vec![1] };
+        return Decoder::with_limits(reader, dictionary, DecoderLimits::default());
     }
 
-    #[inline]
-    fn read_length(&mut self) -> DecoderResult<usize> {
-        let result = match try!(self.reader.read_u8()) {
-            x if x < 0xEF => x as usize,
-            0xF1 => try!(self.reader.read_u8()) as usize,
-            0xF2 => try!(self.reader.read_u16::<LittleEndian>()) as usize,
-            0xF3 => try!(self.reader.read_u32::<LittleEndian>()) as usize,
-            0xF4 => try!(self.reader.read_u64::<LittleEndian>()) as usize,
-            _ => {
-                return Err(DecoderError::StreamError(ErrorCode::InvalidLength));
+    pub fn with_limits(reader: &'a mut BorrowRead<'a>, dictionary: &'a [&'a str], limits: DecoderLimits) -> Decoder<'a> {
+        return Decoder::with_options(reader, dictionary, DecoderOptions { limits: limits, ..DecoderOptions::default() });
+    }
+
+    pub fn with_options(reader: &'a mut BorrowRead<'a>, dictionary: &'a [&'a str], options: DecoderOptions) -> Decoder<'a> {
+        let buffer = reader.fill_buffer();
+
+        return Decoder::with_input(Some(reader), buffer, dictionary, options);
+    }
+
+    pub(crate) fn with_slice(input: &'a [u8], dictionary: &'a [&'a str], options: DecoderOptions) -> Decoder<'a> {
+        return Decoder::with_input(None, input, dictionary, options);
+    }
+
+    fn with_input(reader: Option<&'a mut BorrowRead<'a>>, buffer: &'a [u8], dictionary: &'a [&'a str], options: DecoderOptions) -> Decoder<'a> {
+        let mut indices = collections::HashMap::new();
+
+        if options.strict {
+            for (i, e) in dictionary.iter().enumerate() {
+                indices.entry(*e).or_insert(i);
             }
+        }
+
+        return Decoder {
+            reader: reader,
+            buffer: buffer,
+            dictionary: dictionary,
+            stack: vec![StackSize::U64(1)],
+            path: Path::new(),
+            options: options,
+            start: buffer.len(),
+            failed: false,
+            indices: indices,
+            keys: vec![(false, None)],
+            unit_variant: None
         };
+    }
 
-        return Ok(result);
+    #[inline]
+    fn offset(&self) -> usize {
+        return self.start - self.buffer.len();
+    }
+
+    #[inline]
+    fn sync(&mut self) {
+        if let Some(ref mut reader) = self.reader {
+            let consumed = reader.fill_buffer().len() - self.buffer.len();
+
+            reader.consume(consumed);
+        }
+    }
+
+    fn checkpoint(&self) -> Checkpoint<'a> {
+        return Checkpoint {
+            buffer: self.buffer,
+            depth: self.stack.len(),
+            top: self.stack.last().cloned(),
+            keys: self.keys.len(),
+            key: self.keys.last().cloned()
+        };
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<'a>) {
+        self.buffer = checkpoint.buffer;
+
+        if let Some(top) = checkpoint.top {
+            self.stack.truncate(checkpoint.depth - 1);
+            self.stack.push(top);
+        }
+
+        if let Some(key) = checkpoint.key {
+            self.keys.truncate(checkpoint.keys - 1);
+            self.keys.push(key);
+        }
     }
 
     #[inline]
-    fn read_binary(&mut self) -> DecoderResult<&'a [u8]> {
-        let length = try!(self.read_length());
+    fn check_canonical(&self, canonical: bool) -> DecoderResult<()> {
+        if self.options.strict && !canonical {
+            return Err(DecoderError::StreamError(ErrorCode::NonCanonical));
+        }
 
-        let buffer = self.reader.fill_buffer();
+        return Ok(());
+    }
+
+    #[inline]
+    fn read_binary_data(&mut self, length: u64) -> DecoderResult<&'a [u8]> {
+        let length = try!(self.options.limits.check_length(length));
 
-        if length > buffer.len() {
+        if length > self.buffer.len() {
             return Err(DecoderError::StreamError(ErrorCode::UnexpectedEOF));
         }
 
-        let buffer = &buffer[..length];
+        let (buffer, rest) = self.buffer.split_at(length);
 
-        self.reader.consume(length);
+        self.buffer = rest;
 
         return Ok(buffer);
     }
 
     #[inline]
-    fn read_string(&mut self) -> DecoderResult<&'a str> {
-        let length = match try!(self.reader.read_u8()) {
-            x if x < 0xEF => x as usize,
-            0xF1 => try!(self.reader.read_u8()) as usize,
-            0xF2 => try!(self.reader.read_u16::<LittleEndian>()) as usize,
-            0xF3 => try!(self.reader.read_u32::<LittleEndian>()) as usize,
-            0xF4 => try!(self.reader.read_u64::<LittleEndian>()) as usize,
-            0xF5 => {
-                let index = try!(self.reader.read_u8());
-
-                return self.read_dictionary(index as usize);
+    fn read_string_data(&mut self, length: u64) -> DecoderResult<&'a str> {
+        let buffer = try!(self.read_binary_data(length));
+
+        let s = match str::from_utf8(buffer) {
+            Ok(s) => s,
+            Err(_) => {
+                return Err(DecoderError::StreamError(ErrorCode::InvalidUTF8));
             }
-            0xF6 => {
-                let index = try!(self.reader.read_u16::<LittleEndian>());
+        };
+
+        try!(self.check_canonical(!self.indices.contains_key(s)));
+
+        return Ok(s);
+    }
 
-                return self.read_dictionary(index as usize);
+    #[inline]
+    fn read_dictionary(&mut self, index: u64) -> DecoderResult<&'a str> {
+        let s = try!(token::read_dictionary(self.dictionary, index));
+
+        try!(self.check_canonical(self.indices.get(s) == Some(&(index as usize))));
+
+        return Ok(s);
+    }
+
+    #[inline]
+    fn push_stack(&mut self, remaining: StackSize) -> DecoderResult<()> {
+        self.stack.push(try!(remaining.remove_one()));
+
+        return Ok(());
+    }
+
+    #[inline]
+    fn push_container(&mut self, remaining: StackSize, size: Size, modulo: usize, required: usize) -> DecoderResult<()> {
+        try!(self.options.limits.check_depth(self.stack.len() + 1));
+
+        let container = try!(StackSize::from_size(size, modulo, required, &self.options.limits));
+
+        try!(self.push_stack(remaining));
+
+        self.stack.push(container);
+
+        return Ok(());
+    }
+
+    pub fn read(&mut self) -> DecoderResult<Option<Event<'a>>> {
+        let offset = self.offset();
+        let result = self.read_checked();
+
+        self.sync();
+
+        return match result {
+            Ok(result) => {
+                if let Some(ref event) = result {
+                    self.path.track(event, |s| s);
+                }
+
+                if self.options.widen {
+                    return Ok(result.map(Event::widen));
+                }
+
+                Ok(result)
             }
-            0xF7 => {
-                let index = try!(self.reader.read_u32::<LittleEndian>());
+            Err(e) => Err(self.path.locate(offset as u64, e))
+        };
+    }
+
+    fn read_checked(&mut self) -> DecoderResult<Option<Event<'a>>> {
+        if self.options.strict {
+            try!(self.check_key_order());
+        }
+
+        let result = try!(self.read_event());
 
-                return self.read_dictionary(index as usize);
+        try!(self.options.limits.check_size(self.offset() as u64));
+
+        if self.options.strict {
+            match result {
+                Some(Event::End) => {
+                    self.keys.pop();
+                }
+                Some(Event::StartArray(_)) |
+                Some(Event::StartStruct(_)) => self.keys.push((false, None)),
+                Some(Event::StartMap(_)) |
+                Some(Event::StartOpenStruct(_)) => self.keys.push((true, None)),
+                _ => ()
             }
-            0xF8 => {
-                let index = try!(self.reader.read_u64::<LittleEndian>());
+        }
+
+        return Ok(result);
+    }
+
+    fn check_key_order(&mut self) -> DecoderResult<()> {
+        let at_key = match (self.stack.last(), self.keys.last()) {
+            (Some(&StackSize::U64(n)), Some(&(true, _))) => n > 0 && n % 2 == 0,
+            _ => false
+        };
+
+        if !at_key {
+            return Ok(());
+        }
+
+        let length = {
+            let mut probe = self.probe();
+
+            try!(probe.skip_checked());
+
+            probe.offset() - self.offset()
+        };
+
+        let key = &self.buffer[..length];
 
-                return self.read_dictionary(index as usize);
+        if let Some(&mut (_, ref mut last)) = self.keys.last_mut() {
+            if let Some(previous) = *last {
+                if previous >= key {
+                    return Err(DecoderError::StreamError(ErrorCode::NonCanonical));
+                }
+            }
+
+            *last = Some(key);
+        }
+
+        return Ok(());
+    }
+
+    fn read_event(&mut self) -> DecoderResult<Option<Event<'a>>> {
+        let remaining = match self.stack.pop() {
+            Some(remaining) => remaining,
+            None => {
+                return Err(DecoderError::StreamError(ErrorCode::EndOfStream));
+            }
+        };
+
+        if remaining == StackSize::U64(0) {
+            if self.stack.len() == 0 {
+                return Ok(None);
             }
-            _ => {
-                return Err(DecoderError::StreamError(ErrorCode::InvalidLength));
+
+            return Ok(Some(Event::End));
+        }
+
+        let result = match try!(token::read_token(&mut self.buffer, self.options.strict)) {
+            token::Token::Event(Event::End) => {
+                if remaining.accepts_end() {
+                    return Ok(Some(Event::End));
+                }
+
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
             }
+            token::Token::Event(event) => event,
+            token::Token::Binary(length) => Event::Binary(try!(self.read_binary_data(length))),
+            token::Token::Fixnum(length) => {
+                let result = Event::Fixnum(try!(self.read_binary_data(length)));
+
+                try!(self.check_canonical(canonical::is_canonical(&result)));
+
+                result
+            }
+            token::Token::String(length) => Event::String(try!(self.read_string_data(length))),
+            token::Token::Dictionary(index) => Event::String(try!(self.read_dictionary(index)))
         };
 
-        return self.read_string_data(length);
+        match token::container(&result) {
+            Some((size, modulo, required)) => try!(self.push_container(remaining, size, modulo, required)),
+            None => try!(self.push_stack(remaining))
+        }
+
+        return Ok(Some(result));
     }
 
-    #[inline]
-    fn read_string_data(&mut self, length: usize) -> DecoderResult<&'a str> {
-        let buffer = self.reader.fill_buffer();
+    pub fn peek(&mut self) -> DecoderResult<Option<Event<'a>>> {
+        let offset = self.offset();
+        let checkpoint = self.checkpoint();
+        let result = self.read_checked();
 
-        if length > buffer.len() {
-            return Err(DecoderError::StreamError(ErrorCode::UnexpectedEOF));
+        self.restore(checkpoint);
+
+        return match result {
+            Ok(result) if self.options.widen => Ok(result.map(Event::widen)),
+            Ok(result) => Ok(result),
+            Err(e) => Err(self.path.locate(offset as u64, e))
+        };
+    }
+
+    pub fn skip_value(&mut self) -> DecoderResult<()> {
+        let offset = self.offset();
+        let checkpoint = self.checkpoint();
+        let result = self.skip_checked();
+
+        if result.is_err() {
+            self.restore(checkpoint);
         }
 
-        let buffer = &buffer[..length];
+        self.sync();
 
-        self.reader.consume(length);
+        return match result {
+            Ok(()) => {
+                self.path.advance(|| None);
 
-        return match str::from_utf8(buffer) {
-            Ok(s) => Ok(s),
-            Err(_) => Err(DecoderError::StreamError(ErrorCode::InvalidUTF8))
+                Ok(())
+            }
+            Err(e) => Err(self.path.locate(offset as u64, e))
         };
     }
 
-    #[inline]
-    fn read_dictionary(&mut self, index: usize) -> DecoderResult<&'a str> {
-        return match self.dictionary.get(index) {
-            Some(s) => Ok(s),
-            None => Err(DecoderError::StreamError(ErrorCode::InvalidDictionaryIndex))
+    pub(crate) fn remaining(&mut self) -> DecoderResult<u64> {
+        return match self.stack.last() {
+            Some(&StackSize::U64(n)) | Some(&StackSize::Counted(n)) => Ok(n as u64),
+            Some(&StackSize::Streaming(..)) => {
+                let count = try!(self.count_streaming());
+
+                self.stack.pop();
+                self.stack.push(StackSize::Counted(count as usize));
+
+                Ok(count)
+            }
+            None => Ok(0)
         };
     }
 
-    #[inline]
-    fn push_stack(&mut self, remaining: usize) {
-        self.stack.push(remaining);
+    fn count_streaming(&self) -> DecoderResult<u64> {
+        let mut probe = self.probe();
+
+        let mut count = 0;
+
+        while try!(probe.peek()) != Some(Event::End) {
+            try!(probe.skip_value());
+            count += 1;
+        }
+
+        return Ok(count);
     }
 
-    pub fn read(&mut self) -> DecoderResult<Option<Event<'a>>> {
-        match self.stack.pop() {
-            Some(remaining) => {
-                if remaining == 0 {
-                    if self.stack.len() == 0 {
-                        return Ok(None)
-                    } else {
-                        return Ok(Some(Event::End))
-                    }
+    fn probe(&self) -> Decoder<'a> {
+        return Decoder {
+            reader: None,
+            buffer: self.buffer,
+            dictionary: self.dictionary,
+            stack: self.stack.clone(),
+            path: self.path.clone(),
+            options: DecoderOptions { strict: false, ..self.options },
+            start: self.start,
+            failed: false,
+            indices: collections::HashMap::new(),
+            keys: Vec::new(),
+            unit_variant: None
+        };
+    }
+
+    pub(crate) fn at_end(&self) -> bool {
+        return match self.stack.last() {
+            Some(&StackSize::U64(0)) | Some(&StackSize::Counted(0)) => true,
+            Some(&StackSize::Streaming(..)) => self.buffer.first() == Some(&0x00),
+            _ => false
+        };
+    }
+
+    pub(crate) fn at_nil(&self) -> bool {
+        return match self.stack.last() {
+            Some(&StackSize::U64(0)) | Some(&StackSize::Counted(0)) | None => false,
+            _ => self.buffer.first() == Some(&0x01)
+        };
+    }
+
+    pub(crate) fn locate_error(&self, error: DecoderError) -> DecoderError {
+        return self.path.locate(self.offset() as u64, error);
+    }
+
+    pub fn read_raw_value(&mut self) -> DecoderResult<&'a [u8]> {
+        let buffer = self.buffer;
+
+        try!(self.skip_value());
+
+        let length = buffer.len() - self.buffer.len();
+
+        return Ok(&buffer[..length]);
+    }
+
+    pub fn expect_nil(&mut self) -> DecoderResult<()> {
+        return self.expect("nil", |event| match *event {
+            Event::Nil => Some(()),
+            _ => None
+        });
+    }
+
+    pub fn expect_bool(&mut self) -> DecoderResult<bool> {
+        return self.expect("boolean", |event| match *event {
+            Event::Boolean(v) => Some(v),
+            _ => None
+        });
+    }
+
+    expect_integer!(expect_u8, u8, "u8", U8);
+    expect_integer!(expect_u16, u16, "u16", U8, U16);
+    expect_integer!(expect_u32, u32, "u32", U8, U16, U32);
+    expect_integer!(expect_u64, u64, "u64", U8, U16, U32, U64);
+    expect_integer!(expect_i8, i8, "i8", I8);
+    expect_integer!(expect_i16, i16, "i16", U8, I8, I16);
+    expect_integer!(expect_i32, i32, "i32", U8, U16, I8, I16, I32);
+    expect_integer!(expect_i64, i64, "i64", U8, U16, U32, I8, I16, I32, I64);
+
+    pub fn expect_f32(&mut self) -> DecoderResult<f32> {
+        return self.expect("f32", |event| match *event {
+            Event::F32(v) => Some(v),
+            _ => None
+        });
+    }
+
+    pub fn expect_f64(&mut self) -> DecoderResult<f64> {
+        return self.expect("f64", |event| match *event {
+            Event::F32(v) => Some(v as f64),
+            Event::F64(v) => Some(v),
+            _ => None
+        });
+    }
+
+    pub fn expect_fixnum(&mut self) -> DecoderResult<&'a [u8]> {
+        return self.expect("fixnum", |event| match *event {
+            Event::Fixnum(v) => Some(v),
+            _ => None
+        });
+    }
+
+    pub fn expect_binary(&mut self) -> DecoderResult<&'a [u8]> {
+        return self.expect("binary", |event| match *event {
+            Event::Binary(v) => Some(v),
+            _ => None
+        });
+    }
+
+    pub fn expect_str(&mut self) -> DecoderResult<&'a str> {
+        return self.expect("string", |event| match *event {
+            Event::String(v) => Some(v),
+            _ => None
+        });
+    }
+
+    pub fn expect_array(&mut self) -> DecoderResult<Size> {
+        return self.expect("array", |event| match *event {
+            Event::StartArray(size) => Some(size),
+            _ => None
+        });
+    }
+
+    pub fn expect_map(&mut self) -> DecoderResult<Size> {
+        return self.expect("map", |event| match *event {
+            Event::StartMap(size) => Some(size),
+            _ => None
+        });
+    }
+
+    pub fn expect_struct(&mut self, name: &str) -> DecoderResult<Size> {
+        return self.expect_named("struct", name, |event| match *event {
+            Event::StartStruct(size) => Some(size),
+            _ => None
+        });
+    }
+
+    pub fn expect_open_struct(&mut self, name: &str) -> DecoderResult<Size> {
+        return self.expect_named("open struct", name, |event| match *event {
+            Event::StartOpenStruct(size) => Some(size),
+            _ => None
+        });
+    }
+
+    pub fn expect_end(&mut self) -> DecoderResult<()> {
+        return self.expect("end", |event| match *event {
+            Event::End => Some(()),
+            _ => None
+        });
+    }
+
+    pub fn read_optional<T, F>(&mut self, f: F) -> DecoderResult<Option<T>>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        if try!(self.peek()) == Some(Event::Nil) {
+            try!(self.read());
+
+            return Ok(None);
+        }
+
+        return f(self).map(Some);
+    }
+
+    pub fn drive<V: Visitor<'a>>(&mut self, visitor: &mut V) -> DecoderResult<()> {
+        while let Some(event) = try!(self.read()) {
+            try!(match event {
+                Event::Nil => visitor.visit_nil(),
+                Event::Boolean(v) => visitor.visit_bool(v),
+                Event::U8(v) => visitor.visit_u8(v),
+                Event::U16(v) => visitor.visit_u16(v),
+                Event::U32(v) => visitor.visit_u32(v),
+                Event::U64(v) => visitor.visit_u64(v),
+                Event::I8(v) => visitor.visit_i8(v),
+                Event::I16(v) => visitor.visit_i16(v),
+                Event::I32(v) => visitor.visit_i32(v),
+                Event::I64(v) => visitor.visit_i64(v),
+                Event::Fixnum(v) => visitor.visit_fixnum(v),
+                Event::F32(v) => visitor.visit_f32(v),
+                Event::F64(v) => visitor.visit_f64(v),
+                Event::Binary(v) => visitor.visit_binary(v),
+                Event::String(v) => visitor.visit_str(v),
+                Event::StartArray(size) => visitor.begin_array(size),
+                Event::StartStruct(size) => {
+                    let name = try!(self.expect_str());
+                    visitor.begin_struct(name, size)
+                }
+                Event::StartMap(size) => visitor.begin_map(size),
+                Event::StartOpenStruct(size) => {
+                    let name = try!(self.expect_str());
+                    visitor.begin_open_struct(name, size)
                 }
+                Event::End => visitor.end()
+            });
+        }
 
-                let result = match try!(self.reader.read_u8()) {
-                    0x00 => panic!("Not implemented yet"), // End
-                    0x01 => Event::Nil,
-                    0x02 => Event::Boolean(false),
-                    0x03 => Event::Boolean(true),
-                    0x08 => Event::Binary(try!(self.read_binary())),
-                    0x09 => Event::String(try!(self.read_string())),
-                    0x0A => {
-                        let length = try!(self.read_length());
+        return Ok(());
+    }
 
-                        self.push_stack(remaining - 1);
-                        self.push_stack(length);
+    pub(crate) fn expect_named<T, F>(&mut self, expected: &str, name: &str, f: F) -> DecoderResult<T>
+        where F: FnOnce(&Event<'a>) -> Option<T>
+    {
+        let offset = self.offset();
+        let checkpoint = self.checkpoint();
 
-                        return Ok(Some(Event::StartArray(Size::U64(length as u64))));
-                    }
-                    0x0B => {
-                        let length = try!(self.read_length());
+        let value = match self.expect_event(expected, f) {
+            Ok((value, event)) => {
+                self.path.track(&event, |s| s);
 
-                        self.push_stack(remaining - 1);
-                        self.push_stack(length + 1);
+                if let Some((_, _, 0)) = token::container(&event) {
+                    self.sync();
 
-                        return Ok(Some(Event::StartStruct(Size::U64(length as u64))));
-                    }
-                    0x0C => {
-                        let length = try!(self.read_length());
+                    return Ok(value);
+                }
 
-                        self.push_stack(remaining - 1);
-                        self.push_stack(2 * length);
+                value
+            }
+            Err(e) => {
+                self.sync();
 
-                        return Ok(Some(Event::StartMap(Size::U64(length as u64))));
-                    }
-                    0x0D => {
-                        let length = try!(self.read_length());
+                return Err(self.path.locate(offset as u64, e));
+            }
+        };
 
-                        self.push_stack(remaining - 1);
-                        self.push_stack(2 * length + 1);
+        let offset = self.offset();
+        let expected = format!("name {:?}", name);
 
-                        return Ok(Some(Event::StartOpenStruct(Size::U64(length as u64))));
-                    }
-                    0x10 => Event::U8(try!(self.reader.read_u8())),
-                    0x11 => Event::U16(try!(self.reader.read_u16::<LittleEndian>())),
-                    0x12 => Event::U32(try!(self.reader.read_u32::<LittleEndian>())),
-                    0x13 => Event::U64(try!(self.reader.read_u64::<LittleEndian>())),
-                    0x14 => Event::I8(try!(self.reader.read_i8())),
-                    0x15 => Event::I16(try!(self.reader.read_i16::<LittleEndian>())),
-                    0x16 => Event::I32(try!(self.reader.read_i32::<LittleEndian>())),
-                    0x17 => Event::I64(try!(self.reader.read_i64::<LittleEndian>())),
-                    0x18 => panic!("Not implemented yet"), // Fixnum
-                    0x1A => Event::F32(try!(self.reader.read_f32::<LittleEndian>())),
-                    0x1B => Event::F64(try!(self.reader.read_f64::<LittleEndian>())),
-                    x if x & 0b10000000 == 0b10000000 => Event::String(try!(self.read_dictionary(x as usize & 0b01111111))),
-                    x if x & 0b11100000 == 0b01100000 => Event::String(try!(self.read_string_data(x as usize & 0b00011111))),
-                    x if x & 0b11110000 == 0b00100000 => {
-                        let length = x as usize & 0b00001111;
-
-                        self.push_stack(remaining - 1);
-                        self.push_stack(length);
-
-                        return Ok(Some(Event::StartArray((Size::U64(length as u64)))));
-                    },
-                    x if x & 0b11110000 == 0b00110000 => {
-                        let length = x as usize & 0b00001111;
-
-                        self.push_stack(remaining - 1);
-                        self.push_stack(2 * length);
-
-                        return Ok(Some(Event::StartMap(Size::U64(length as u64))));
-                    }
-                    _ => {
-                        return Err(DecoderError::StreamError(ErrorCode::InvalidType));
-                    }
-                };
+        let result = self.expect_event(&expected, |event| match *event {
+            Event::String(v) if v == name => Some(()),
+            _ => None
+        });
+
+        return match result {
+            Ok((_, event)) => {
+                self.path.track(&event, |s| s);
+                self.sync();
+
+                Ok(value)
+            }
+            Err(e) => {
+                let error = self.path.locate(offset as u64, e);
+
+                self.path.discard();
+                self.restore(checkpoint);
+                self.sync();
+
+                Err(error)
+            }
+        };
+    }
 
-                self.push_stack(remaining - 1);
+    pub(crate) fn expect<T, F>(&mut self, expected: &str, f: F) -> DecoderResult<T>
+        where F: FnOnce(&Event<'a>) -> Option<T>
+    {
+        let offset = self.offset();
+        let result = self.expect_event(expected, f);
 
-                return Ok(Some(result));
+        self.sync();
+
+        return match result {
+            Ok((value, event)) => {
+                self.path.track(&event, |s| s);
+
+                Ok(value)
+            }
+            Err(e) => Err(self.path.locate(offset as u64, e))
+        };
+    }
+
+    fn expect_event<T, F>(&mut self, expected: &str, f: F) -> DecoderResult<(T, Event<'a>)>
+        where F: FnOnce(&Event<'a>) -> Option<T>
+    {
+        let checkpoint = self.checkpoint();
+
+        let found = match try!(self.read_checked()) {
+            Some(event) => {
+                if let Some(value) = f(&event) {
+                    return Ok((value, event));
+                }
+
+                describe(&event)
+            }
+            None => "end of stream".to_string()
+        };
+
+        self.restore(checkpoint);
+
+        return Err(DecoderError::TypeMismatch(expected.to_string(), found));
+    }
+
+    fn skip_checked(&mut self) -> DecoderResult<()> {
+        let depth = self.stack.len();
+
+        match self.stack.last() {
+            Some(&StackSize::U64(0)) if depth == 1 => {
+                return Err(DecoderError::StreamError(ErrorCode::EndOfStream));
             }
+            Some(&StackSize::U64(0)) => {
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+            }
+            Some(&StackSize::Streaming(..)) |
+            Some(&StackSize::Counted(_)) if self.buffer.first() == Some(&0x00) => {
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+            }
+            Some(_) => (),
             None => {
                 return Err(DecoderError::StreamError(ErrorCode::EndOfStream));
             }
         }
+
+        if self.options.strict {
+            try!(self.read_checked());
+
+            while self.stack.len() > depth {
+                try!(self.read_checked());
+            }
+
+            return Ok(());
+        }
+
+        try!(self.skip_event());
+
+        while self.stack.len() > depth {
+            try!(self.skip_event());
+        }
+
+        return self.options.limits.check_size(self.offset() as u64);
+    }
+
+    #[inline]
+    fn skip_bytes(&mut self, length: u64) -> DecoderResult<()> {
+        let length = try!(self.options.limits.check_length(length));
+
+        if length > self.buffer.len() {
+            return Err(DecoderError::StreamError(ErrorCode::UnexpectedEOF));
+        }
+
+        self.buffer = &self.buffer[length..];
+
+        return Ok(());
     }
+
+    fn skip_event(&mut self) -> DecoderResult<()> {
+        let remaining = match self.stack.pop() {
+            Some(remaining) => remaining,
+            None => {
+                return Err(DecoderError::StreamError(ErrorCode::EndOfStream));
+            }
+        };
+
+        if remaining == StackSize::U64(0) {
+            return Ok(());
+        }
+
+        match try!(token::read_token(&mut self.buffer, self.options.strict)) {
+            token::Token::Event(Event::End) => {
+                if remaining.accepts_end() {
+                    return Ok(());
+                }
+
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+            }
+            token::Token::Event(event) => {
+                if let Some((size, modulo, required)) = token::container(&event) {
+                    return self.push_container(remaining, size, modulo, required);
+                }
+            }
+            token::Token::Binary(length) |
+            token::Token::Fixnum(length) |
+            token::Token::String(length) => try!(self.skip_bytes(length)),
+            token::Token::Dictionary(_) => ()
+        }
+
+        return self.push_stack(remaining);
+    }
+}
+
+fn describe(event: &Event) -> String {
+    let name = match *event {
+        Event::Nil => "nil",
+        Event::Boolean(_) => "boolean",
+        Event::U8(_) => "u8",
+        Event::U16(_) => "u16",
+        Event::U32(_) => "u32",
+        Event::U64(_) => "u64",
+        Event::I8(_) => "i8",
+        Event::I16(_) => "i16",
+        Event::I32(_) => "i32",
+        Event::I64(_) => "i64",
+        Event::Fixnum(_) => "fixnum",
+        Event::F32(_) => "f32",
+        Event::F64(_) => "f64",
+        Event::Binary(_) => "binary",
+        Event::String(v) => return format!("string {:?}", v),
+        Event::StartArray(_) => "array",
+        Event::StartStruct(_) => "struct",
+        Event::StartMap(_) => "map",
+        Event::StartOpenStruct(_) => "open struct",
+        Event::End => "end"
+    };
+
+    return name.to_string();
 }
 
 impl<'a> Iterator for Decoder<'a> {
-    type Item = Event<'a>;
+    type Item = DecoderResult<Event<'a>>;
 
-    fn next(&mut self) -> Option<Event<'a>> {
-        return self.read().unwrap();
+    fn next(&mut self) -> Option<DecoderResult<Event<'a>>> {
+        if self.failed {
+            return None;
+        }
+
+        return match self.read() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.failed = true;
+
+                Some(Err(e))
+            }
+        };
     }
 }
 
@@ -257,6 +831,10 @@ mod tests {
 
     use ::{Event, Size};
 
+    use decoder_error::{ErrorCode, DecoderError, DecoderResult, Location};
+    use decoder_limits::DecoderLimits;
+    use decoder_options::DecoderOptions;
+
     use super::Decoder;
 
     macro_rules! basic_test {
@@ -271,7 +849,7 @@ mod tests {
                 let mut cursor = io::Cursor::new(&*data);
 
                 let decoder = Decoder::new(&mut cursor, &dictionary[..]);
-                let events: Vec<Event> = decoder.collect();
+                let events: Vec<Event> = decoder.collect::<DecoderResult<_>>().unwrap();
 
                 assert_eq!(events, $output);
             }
@@ -282,15 +860,24 @@ mod tests {
     basic_test!(decodes_false, vec![0x02], vec![Event::Boolean(false)]);
     basic_test!(decodes_true, vec![0x03], vec![Event::Boolean(true)]);
     basic_test!(decodes_binary, vec![0x08, 0x04, 0x01, 0x02, 0x03, 0x04], vec![Event::Binary(&vec![0x01, 0x02, 0x03, 0x04])]);
+    basic_test!(decodes_u8_length_binary, vec![0x08, 0xF1, 0x01, 0x55], vec![Event::Binary(&vec![0x55])]);
+    basic_test!(decodes_u16_length_binary, vec![0x08, 0xF2, 0x01, 0x00, 0x55], vec![Event::Binary(&vec![0x55])]);
+    basic_test!(decodes_u32_length_binary, vec![0x08, 0xF3, 0x01, 0x00, 0x00, 0x00, 0x55], vec![Event::Binary(&vec![0x55])]);
+    basic_test!(decodes_u64_length_binary, vec![0x08, 0xF4, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x55], vec![Event::Binary(&vec![0x55])]);
     basic_test!(decodes_string, vec![0x64, 0xF0, 0x9F, 0x8D, 0xAA], vec![Event::String("🍪")]);
     basic_test!(decodes_dictionary_string, vec![0x80], vec![Event::String("🍪")], vec!["🍪"]);
     basic_test!(decodes_noncanonical_string, vec![0x09, 0x04, 0xF0, 0x9F, 0x8D, 0xAA], vec![Event::String("🍪")]);
     basic_test!(decodes_array, vec![0x21, 0x01], vec![Event::StartArray(Size::U64(1)), Event::Nil, Event::End]);
     basic_test!(decodes_noncanonical_array, vec![0x0A, 0x02, 0x01, 0x01], vec![Event::StartArray(Size::U64(2)), Event::Nil, Event::Nil, Event::End]);
+    basic_test!(decodes_streaming_array, vec![0x0A, 0xF0, 0x01, 0x01, 0x00], vec![Event::StartArray(Size::Streaming), Event::Nil, Event::Nil, Event::End]);
+    basic_test!(decodes_empty_streaming_array, vec![0x0A, 0xF0, 0x00], vec![Event::StartArray(Size::Streaming), Event::End]);
     basic_test!(decodes_struct, vec![0x0B, 0x01, 0x80, 0x02], vec![Event::StartStruct(Size::U64(1)), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
+    basic_test!(decodes_streaming_struct, vec![0x0B, 0xF0, 0x80, 0x02, 0x00], vec![Event::StartStruct(Size::Streaming), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(decodes_map, vec![0x0C, 0x01, 0x80, 0x02], vec![Event::StartMap(Size::U64(1)), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(decodes_noncanonical_map, vec![0x0C, 0x01, 0x80, 0x02], vec![Event::StartMap(Size::U64(1)), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
+    basic_test!(decodes_streaming_map, vec![0x0C, 0xF0, 0x80, 0x02, 0x00], vec![Event::StartMap(Size::Streaming), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(decodes_open_struct, vec![0x0D, 0x01, 0x80, 0x80, 0x02], vec![Event::StartOpenStruct(Size::U64(1)), Event::String("🍪"), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
+    basic_test!(decodes_streaming_open_struct, vec![0x0D, 0xF0, 0x80, 0x80, 0x02, 0x00], vec![Event::StartOpenStruct(Size::Streaming), Event::String("🍪"), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(decodes_u8, vec![0x10, 0x50], vec![Event::U8(0x50)]);
     basic_test!(decodes_u16, vec![0x11, 0x50, 0x51], vec![Event::U16(0x5150)]);
     basic_test!(decodes_u32, vec![0x12, 0x50, 0x51, 0x52, 0x53], vec![Event::U32(0x53525150)]);
@@ -299,7 +886,363 @@ mod tests {
     basic_test!(decodes_i16, vec![0x15, 0x50, 0x51], vec![Event::I16(0x5150)]);
     basic_test!(decodes_i32, vec![0x16, 0x50, 0x51, 0x52, 0x53], vec![Event::I32(0x53525150)]);
     basic_test!(decodes_i64, vec![0x17, 0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57], vec![Event::I64(0x5756555453525150)]);
+    basic_test!(decodes_fixnum, vec![0x18, 0x02, 0x80, 0x00], vec![Event::Fixnum(&vec![0x80, 0x00])]);
     basic_test!(decodes_f32, vec![0x1A, 0x00, 0x00, 0x80, 0x3F], vec![Event::F32(1.0)]);
     basic_test!(decodes_f64, vec![0x1B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0, 0x3F], vec![Event::F64(1.0)]);
 
+    macro_rules! error_test {
+        ($identifier:ident, $input:expr, $error:expr) => {
+            error_test!($identifier, $input, $error, DecoderLimits::unlimited());
+        };
+        ($identifier:ident, $input:expr, $error:expr, $limits:expr) => {
+            #[test]
+            fn $identifier() {
+                let data = $input;
+                let dictionary: Vec<&'static str> = vec![];
+                let mut cursor = io::Cursor::new(&*data);
+
+                let mut decoder = Decoder::with_limits(&mut cursor, &dictionary[..], $limits);
+
+                loop {
+                    match decoder.read() {
+                        Ok(Some(_)) => continue,
+                        Ok(None) => panic!("expected {:?}", $error),
+                        Err(e) => {
+                            assert_eq!(e.code(), Some(&$error));
+                            break;
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    error_test!(rejects_end_in_sized_array, vec![0x22, 0x01, 0x00], ErrorCode::InvalidEnd);
+    error_test!(rejects_end_at_root, vec![0x00], ErrorCode::InvalidEnd);
+    error_test!(rejects_empty_streaming_struct, vec![0x0B, 0xF0, 0x00], ErrorCode::InvalidEnd);
+    error_test!(rejects_odd_streaming_map, vec![0x0C, 0xF0, 0x01, 0x00], ErrorCode::InvalidEnd);
+    error_test!(rejects_even_streaming_open_struct, vec![0x0D, 0xF0, 0x01, 0x01, 0x00], ErrorCode::InvalidEnd);
+    error_test!(rejects_streaming_binary, vec![0x08, 0xF0], ErrorCode::InvalidLength);
+    error_test!(rejects_overflowing_length, vec![0x0D, 0xF4, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF], ErrorCode::InvalidLength);
+    error_test!(rejects_deep_nesting, vec![0x21, 0x21, 0x21, 0x01], ErrorCode::DepthLimitExceeded, DecoderLimits { max_depth: 2, ..DecoderLimits::unlimited() });
+    error_test!(rejects_long_string, vec![0x64, 0xF0, 0x9F, 0x8D, 0xAA], ErrorCode::LengthLimitExceeded, DecoderLimits { max_length: 3, ..DecoderLimits::unlimited() });
+    error_test!(rejects_long_binary, vec![0x08, 0xF4, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF], ErrorCode::LengthLimitExceeded, DecoderLimits { max_length: 3, ..DecoderLimits::unlimited() });
+    error_test!(rejects_large_array, vec![0x23, 0x01, 0x01, 0x01], ErrorCode::ElementLimitExceeded, DecoderLimits { max_elements: 2, ..DecoderLimits::unlimited() });
+    error_test!(rejects_large_streaming_map, vec![0x0C, 0xF0, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00], ErrorCode::ElementLimitExceeded, DecoderLimits { max_elements: 2, ..DecoderLimits::unlimited() });
+    error_test!(rejects_large_input, vec![0x22, 0x08, 0x02, 0x01, 0x02, 0x01], ErrorCode::SizeLimitExceeded, DecoderLimits { max_size: 5, ..DecoderLimits::unlimited() });
+
+    #[test]
+    fn accepts_input_within_limits() {
+        let data = vec![0x21, 0x0C, 0xF0, 0x01, 0x62, 0xC3, 0xA5, 0x00];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let limits = DecoderLimits { max_depth: 2, max_length: 2, max_elements: 1, max_size: 8 };
+        let decoder = Decoder::with_limits(&mut cursor, &dictionary[..], limits);
+        let events: Vec<Event> = decoder.collect::<DecoderResult<_>>().unwrap();
+
+        assert_eq!(events, vec![Event::StartArray(Size::U64(1)), Event::StartMap(Size::Streaming), Event::Nil, Event::String("å"), Event::End, Event::End]);
+    }
+
+    #[test]
+    fn stops_iterating_after_error() {
+        let data = vec![0x22, 0x01, 0x04, 0x01];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.next(), Some(Ok(Event::StartArray(Size::U64(2)))));
+        assert_eq!(decoder.next(), Some(Ok(Event::Nil)));
+        let location = Location { offset: 2, path: "[1]".to_string() };
+        let error = DecoderError::LocatedError(location, Box::new(DecoderError::StreamError(ErrorCode::InvalidType)));
+
+        assert_eq!(decoder.next(), Some(Err(error)));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn locates_errors() {
+        let data = vec![0x22, 0x01, 0x0D, 0x01, 0x80, 0x81, 0x22, 0x01, 0x04];
+        let dictionary = vec!["person", "users"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let decoder = Decoder::new(&mut cursor, &dictionary[..]);
+        let error = decoder.collect::<DecoderResult<Vec<Event>>>().unwrap_err();
+
+        assert_eq!(error.code(), Some(&ErrorCode::InvalidType));
+        assert_eq!(error.location(), Some(&Location { offset: 8, path: "[1].users[1]".to_string() }));
+    }
+
+    #[test]
+    fn skips_nested_values() {
+        let data = vec![0x23, 0x0B, 0x02, 0x80, 0x62, 0xC3, 0x28, 0x08, 0xF1, 0x01, 0x55, 0x0A, 0xF0, 0x21, 0x13, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x03];
+        let dictionary = vec!["point"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(3)))));
+        assert_eq!(decoder.skip_value(), Ok(()));
+        assert_eq!(decoder.skip_value(), Ok(()));
+        assert_eq!(decoder.read(), Ok(Some(Event::Boolean(true))));
+        assert_eq!(decoder.skip_value().unwrap_err().code(), Some(&ErrorCode::InvalidEnd));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+        assert_eq!(decoder.skip_value().unwrap_err().code(), Some(&ErrorCode::EndOfStream));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn skips_to_streaming_end() {
+        let data = vec![0x0A, 0xF0, 0x81, 0x01, 0x00];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::Streaming))));
+        assert_eq!(decoder.skip_value(), Ok(()));
+        assert_eq!(decoder.skip_value(), Ok(()));
+        assert_eq!(decoder.skip_value().unwrap_err().code(), Some(&ErrorCode::InvalidEnd));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+    }
+
+    #[test]
+    fn recovers_from_failed_skip() {
+        let data = vec![0x21, 0x22, 0x01];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(1)))));
+        assert_eq!(decoder.skip_value().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(2)))));
+        assert_eq!(decoder.read(), Ok(Some(Event::Nil)));
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+    }
+
+    #[test]
+    fn reports_truncated_raw_values() {
+        let data = vec![0x22, 0x01, 0x08, 0x04, 0x01];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(2)))));
+        assert_eq!(decoder.read_raw_value(), Ok(&[0x01][..]));
+        assert_eq!(decoder.read_raw_value().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+        assert_eq!(decoder.read_raw_value().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+    }
+
+    #[test]
+    fn peeks_without_consuming() {
+        let data = vec![0x0A, 0xF0, 0x0B, 0x00, 0x80, 0x01, 0x00, 0x08, 0x04, 0x01];
+        let dictionary = vec!["point"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.peek(), Ok(Some(Event::StartArray(Size::Streaming))));
+        assert_eq!(decoder.peek(), Ok(Some(Event::StartArray(Size::Streaming))));
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::Streaming))));
+        assert_eq!(decoder.peek(), Ok(Some(Event::StartStruct(Size::U64(0)))));
+        assert_eq!(decoder.read(), Ok(Some(Event::StartStruct(Size::U64(0)))));
+        assert_eq!(decoder.peek(), Ok(Some(Event::String("point"))));
+        assert_eq!(decoder.read(), Ok(Some(Event::String("point"))));
+        assert_eq!(decoder.peek(), Ok(Some(Event::End)));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+        assert_eq!(decoder.peek(), Ok(Some(Event::Nil)));
+        assert_eq!(decoder.read(), Ok(Some(Event::Nil)));
+        assert_eq!(decoder.peek(), Ok(Some(Event::End)));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+        assert_eq!(decoder.peek(), Ok(None));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn peeks_errors() {
+        let data = vec![0x22, 0x00, 0x08, 0x04, 0x01];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(2)))));
+        assert_eq!(decoder.peek().unwrap_err().code(), Some(&ErrorCode::InvalidEnd));
+
+        let data = vec![0x08, 0x04, 0x01];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.peek().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+
+        let data = vec![0x21, 0x21, 0x01];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let limits = DecoderLimits { max_depth: 1, ..DecoderLimits::unlimited() };
+        let mut decoder = Decoder::with_limits(&mut cursor, &dictionary[..], limits);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(1)))));
+        assert_eq!(decoder.peek().unwrap_err().code(), Some(&ErrorCode::DepthLimitExceeded));
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::DepthLimitExceeded));
+    }
+
+    #[test]
+    fn expects_typed_values() {
+        let data = vec![0x0B, 0x04, 0x80, 0x10, 0x07, 0x15, 0xFE, 0xFF, 0x01, 0x21, 0x1A, 0x00, 0x00, 0xC0, 0x3F];
+        let dictionary = vec!["point"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.expect_struct("point"), Ok(Size::U64(4)));
+        assert_eq!(decoder.expect_u32(), Ok(7));
+        assert_eq!(decoder.expect_i64(), Ok(-2));
+        assert_eq!(decoder.read_optional(|d| d.expect_u8()), Ok(None));
+        assert_eq!(decoder.expect_array(), Ok(Size::U64(1)));
+        assert_eq!(decoder.read_optional(|d| d.expect_f64()), Ok(Some(1.5)));
+        assert_eq!(decoder.expect_end(), Ok(()));
+        assert_eq!(decoder.expect_end(), Ok(()));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn reports_type_mismatches() {
+        let data = vec![0x22, 0x12, 0x00, 0x00, 0x01, 0x00, 0x63, 0x61, 0x62, 0x63];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.expect_array(), Ok(Size::U64(2)));
+
+        let location = Location { offset: 1, path: "[0]".to_string() };
+        let error = DecoderError::TypeMismatch("u16".to_string(), "u32".to_string());
+
+        assert_eq!(decoder.expect_u16(), Err(DecoderError::LocatedError(location, Box::new(error))));
+        assert_eq!(decoder.expect_u32(), Ok(0x10000));
+        assert_eq!(decoder.expect_u8().unwrap_err().to_string(),
+                   "invalid type: expected u8, found string \"abc\" at byte 6 ([1])");
+        assert_eq!(decoder.expect_str(), Ok("abc"));
+        assert_eq!(decoder.expect_end(), Ok(()));
+        assert_eq!(decoder.expect_nil().unwrap_err().to_string(),
+                   "invalid type: expected nil, found end of stream at byte 10");
+    }
+
+    #[test]
+    fn restores_state_on_mismatch() {
+        let data = vec![0x21, 0x0B, 0x01, 0x80, 0x10, 0x07];
+        let dictionary = vec!["point"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.expect_array(), Ok(Size::U64(1)));
+        assert_eq!(decoder.expect_map().unwrap_err().to_string(),
+                   "invalid type: expected map, found struct at byte 1 ([0])");
+        assert_eq!(decoder.expect_struct("line").unwrap_err().to_string(),
+                   "invalid type: expected name \"line\", found string \"point\" at byte 3 ([0].<name>)");
+        assert_eq!(decoder.expect_struct("point"), Ok(Size::U64(1)));
+        assert_eq!(decoder.expect_u8(), Ok(7));
+        assert_eq!(decoder.expect_end(), Ok(()));
+        assert_eq!(decoder.expect_end(), Ok(()));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn counts_remaining_values() {
+        let data = vec![0x0A, 0xF0, 0x10, 0x01, 0x21, 0x01, 0x61, 0x61, 0x00];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.remaining(), Ok(1));
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::Streaming))));
+        assert_eq!(decoder.remaining(), Ok(3));
+        assert_eq!(decoder.read(), Ok(Some(Event::U8(1))));
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(1)))));
+        assert_eq!(decoder.remaining(), Ok(1));
+        assert_eq!(decoder.read(), Ok(Some(Event::Nil)));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+        assert_eq!(decoder.remaining(), Ok(1));
+        assert_eq!(decoder.read(), Ok(Some(Event::String("a"))));
+        assert_eq!(decoder.remaining(), Ok(0));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn rejects_noncanonical_input() {
+        let dictionary = vec!["a", "b", "a"];
+        let inputs: Vec<Vec<u8>> = vec![
+            vec![0x09, 0x01, 0x7A],
+            vec![0x61, 0x62],
+            vec![0x82],
+            vec![0x09, 0xF5, 0x01],
+            vec![0x08, 0xF1, 0x01, 0x00],
+            vec![0x0A, 0x01, 0x01],
+            vec![0x0A, 0xF0, 0x00],
+            vec![0x11, 0x01, 0x00],
+            vec![0x1B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0, 0x3F],
+            vec![0x1A, 0x01, 0x00, 0xC0, 0x7F],
+            vec![0x18, 0x01, 0x00],
+            vec![0x32, 0x80, 0x01, 0x61, 0x7A, 0x01],
+            vec![0x32, 0x61, 0x7A, 0x01, 0x61, 0x7A, 0x01]
+        ];
+
+        for data in inputs.iter() {
+            let mut cursor = io::Cursor::new(&**data);
+            let decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+            assert!(decoder.collect::<DecoderResult<Vec<Event>>>().is_ok());
+
+            let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+            let mut cursor = io::Cursor::new(&**data);
+            let decoder = Decoder::with_options(&mut cursor, &dictionary[..], options);
+            let error = decoder.collect::<DecoderResult<Vec<Event>>>().unwrap_err();
+
+            assert_eq!(error.code(), Some(&ErrorCode::NonCanonical), "{:?}", data);
+        }
+
+        let data = vec![0x32, 0x61, 0x7A, 0x01, 0x80, 0x01];
+        let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+        let mut cursor = io::Cursor::new(&*data);
+        let decoder = Decoder::with_options(&mut cursor, &dictionary[..], options);
+
+        assert_eq!(decoder.collect::<DecoderResult<Vec<Event>>>().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn validates_skipped_values() {
+        let dictionary: Vec<&'static str> = vec![];
+        let inputs: Vec<Vec<u8>> = vec![
+            vec![0x21, 0x32, 0x61, 0x7A, 0x01, 0x61, 0x79, 0x01],
+            vec![0x21, 0x21, 0x11, 0x07, 0x00],
+            vec![0x21, 0x21, 0x09, 0x01, 0x7A]
+        ];
+
+        for data in inputs.iter() {
+            let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+            let mut cursor = io::Cursor::new(&**data);
+            let mut decoder = Decoder::with_options(&mut cursor, &dictionary[..], options);
+
+            assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(1)))));
+            assert_eq!(decoder.skip_value().unwrap_err().code(), Some(&ErrorCode::NonCanonical), "{:?}", data);
+        }
+
+        let data = vec![0x21, 0x32, 0x61, 0x79, 0x01, 0x61, 0x7A, 0x01];
+        let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+        let mut cursor = io::Cursor::new(&*data);
+        let mut decoder = Decoder::with_options(&mut cursor, &dictionary[..], options);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartArray(Size::U64(1)))));
+        assert_eq!(decoder.skip_value(), Ok(()));
+        assert_eq!(decoder.read(), Ok(Some(Event::End)));
+        assert_eq!(decoder.read(), Ok(None));
+    }
 }
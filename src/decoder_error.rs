@@ -1,21 +1,126 @@
+use std::error;
+use std::fmt;
 use std::io;
 
 use byteorder;
 
 #[derive(Debug, PartialEq)]
 pub enum ErrorCode {
+    DepthLimitExceeded,
+    ElementLimitExceeded,
     EndOfStream,
+    ExtraField,
     InvalidDictionaryIndex,
+    InvalidEnd,
     InvalidLength,
     InvalidType,
     InvalidUTF8,
-    UnexpectedEOF
+    LengthLimitExceeded,
+    MissingField,
+    NeedMoreData,
+    NonCanonical,
+    Overflow,
+    SizeLimitExceeded,
+    UnexpectedEOF,
+    Unsupported
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match *self {
+            ErrorCode::DepthLimitExceeded => "containers nested deeper than the depth limit",
+            ErrorCode::ElementLimitExceeded => "container has more elements than the element limit",
+            ErrorCode::EndOfStream => "read past the end of the stream",
+            ErrorCode::ExtraField => "unexpected extra field",
+            ErrorCode::InvalidDictionaryIndex => "dictionary index out of range",
+            ErrorCode::InvalidEnd => "end of container in an invalid position",
+            ErrorCode::InvalidLength => "invalid length",
+            ErrorCode::InvalidType => "invalid type",
+            ErrorCode::InvalidUTF8 => "string is not valid UTF-8",
+            ErrorCode::LengthLimitExceeded => "string or binary longer than the length limit",
+            ErrorCode::MissingField => "missing field",
+            ErrorCode::NeedMoreData => "more data is needed to decode the next event",
+            ErrorCode::NonCanonical => "input is not in canonical form",
+            ErrorCode::Overflow => "integer does not fit the requested type",
+            ErrorCode::SizeLimitExceeded => "input larger than the size limit",
+            ErrorCode::UnexpectedEOF => "unexpected end of input",
+            ErrorCode::Unsupported => "option is not supported by this decoder"
+        };
+
+        return f.write_str(message);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub offset: u64,
+    pub path: String
 }
 
 #[derive(Debug)]
 pub enum DecoderError {
     StreamError(ErrorCode),
-    IoError(io::Error)
+    IoError(io::Error),
+    LocatedError(Location, Box<DecoderError>),
+    TypeMismatch(String, String),
+    MissingField(String),
+    ExtraField(String, usize),
+    ApplicationError(String)
+}
+
+impl DecoderError {
+    pub fn code(&self) -> Option<&ErrorCode> {
+        return match *self {
+            DecoderError::StreamError(ref code) => Some(code),
+            DecoderError::IoError(_) => None,
+            DecoderError::LocatedError(_, ref error) => error.code(),
+            DecoderError::TypeMismatch(..) => Some(&ErrorCode::InvalidType),
+            DecoderError::MissingField(..) => Some(&ErrorCode::MissingField),
+            DecoderError::ExtraField(..) => Some(&ErrorCode::ExtraField),
+            DecoderError::ApplicationError(_) => None
+        };
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        return match *self {
+            DecoderError::LocatedError(ref location, _) => Some(location),
+            _ => None
+        };
+    }
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            DecoderError::StreamError(ref code) => write!(f, "{}", code),
+            DecoderError::IoError(ref error) => write!(f, "{}", error),
+            DecoderError::LocatedError(ref location, ref error) if location.path.is_empty() => {
+                write!(f, "{} at byte {}", error, location.offset)
+            }
+            DecoderError::LocatedError(ref location, ref error) => {
+                write!(f, "{} at byte {} ({})", error, location.offset, location.path)
+            }
+            DecoderError::TypeMismatch(ref expected, ref found) => {
+                write!(f, "{}: expected {}, found {}", ErrorCode::InvalidType, expected, found)
+            }
+            DecoderError::MissingField(ref field) => write!(f, "{} {}", ErrorCode::MissingField, field),
+            DecoderError::ExtraField(ref name, len) => {
+                write!(f, "{}: {} has more than {} fields", ErrorCode::ExtraField, name, len)
+            }
+            DecoderError::ApplicationError(ref message) => f.write_str(message)
+        };
+    }
+}
+
+impl error::Error for DecoderError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        return match *self {
+            DecoderError::StreamError(_) => None,
+            DecoderError::IoError(ref error) => Some(error),
+            DecoderError::LocatedError(_, ref error) => error.source(),
+            _ => None
+        };
+    }
 }
 
 impl From<byteorder::Error> for DecoderError {
@@ -34,9 +139,58 @@ impl PartialEq for DecoderError {
     fn eq(&self, other: &DecoderError) -> bool {
         return match (self, other) {
             (&DecoderError::StreamError(ref m0), &DecoderError::StreamError(ref m1)) => m0 == m1,
+            (&DecoderError::IoError(ref e0), &DecoderError::IoError(ref e1)) => e0.kind() == e1.kind(),
+            (&DecoderError::LocatedError(ref l0, ref e0), &DecoderError::LocatedError(ref l1, ref e1)) => l0 == l1 && e0 == e1,
+            (&DecoderError::TypeMismatch(ref x0, ref f0), &DecoderError::TypeMismatch(ref x1, ref f1)) => x0 == x1 && f0 == f1,
+            (&DecoderError::MissingField(ref f0), &DecoderError::MissingField(ref f1)) => f0 == f1,
+            (&DecoderError::ExtraField(ref n0, l0), &DecoderError::ExtraField(ref n1, l1)) => n0 == n1 && l0 == l1,
+            (&DecoderError::ApplicationError(ref m0), &DecoderError::ApplicationError(ref m1)) => m0 == m1,
             _ => false
         };
     }
 }
 
 pub type DecoderResult<T> = Result<T, DecoderError>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::io;
+
+    use super::{ErrorCode, DecoderError, Location};
+
+    #[test]
+    fn displays_location() {
+        let location = Location { offset: 12, path: "[3].users".to_string() };
+        let error = DecoderError::LocatedError(location, Box::new(DecoderError::StreamError(ErrorCode::InvalidType)));
+
+        assert_eq!(error.to_string(), "invalid type at byte 12 ([3].users)");
+    }
+
+    #[test]
+    fn displays_type_mismatch() {
+        let error = DecoderError::TypeMismatch("u32".to_string(), "string".to_string());
+
+        assert_eq!(error.to_string(), "invalid type: expected u32, found string");
+        assert_eq!(error.code(), Some(&ErrorCode::InvalidType));
+    }
+
+    #[test]
+    fn exposes_io_error_as_source() {
+        let location = Location { offset: 0, path: String::new() };
+        let error = DecoderError::LocatedError(location, Box::new(DecoderError::IoError(io::Error::new(io::ErrorKind::Other, "oops"))));
+
+        assert_eq!(error.source().map(|e| e.to_string()), Some("oops".to_string()));
+        assert!(DecoderError::StreamError(ErrorCode::InvalidType).source().is_none());
+    }
+
+    #[test]
+    fn compares_io_errors_by_kind() {
+        let e0 = DecoderError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof, "a"));
+        let e1 = DecoderError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof, "b"));
+        let e2 = DecoderError::IoError(io::Error::new(io::ErrorKind::Other, "a"));
+
+        assert_eq!(e0, e1);
+        assert!(e0 != e2);
+    }
+}
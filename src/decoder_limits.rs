@@ -0,0 +1,57 @@
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecoderLimits {
+    pub max_depth: usize,
+    pub max_length: u64,
+    pub max_elements: u64,
+    pub max_size: u64
+}
+
+impl DecoderLimits {
+    pub fn unlimited() -> DecoderLimits {
+        return DecoderLimits { max_depth: usize::MAX, max_length: u64::MAX, max_elements: u64::MAX, max_size: u64::MAX };
+    }
+
+    pub fn check_depth(&self, depth: usize) -> DecoderResult<()> {
+        if depth > self.max_depth {
+            return Err(DecoderError::StreamError(ErrorCode::DepthLimitExceeded));
+        }
+
+        return Ok(());
+    }
+
+    pub fn check_length(&self, length: u64) -> DecoderResult<usize> {
+        if length > self.max_length {
+            return Err(DecoderError::StreamError(ErrorCode::LengthLimitExceeded));
+        }
+
+        if length > usize::MAX as u64 {
+            return Err(DecoderError::StreamError(ErrorCode::InvalidLength));
+        }
+
+        return Ok(length as usize);
+    }
+
+    pub fn check_elements(&self, elements: u64) -> DecoderResult<()> {
+        if elements > self.max_elements {
+            return Err(DecoderError::StreamError(ErrorCode::ElementLimitExceeded));
+        }
+
+        return Ok(());
+    }
+
+    pub fn check_size(&self, size: u64) -> DecoderResult<()> {
+        if size > self.max_size {
+            return Err(DecoderError::StreamError(ErrorCode::SizeLimitExceeded));
+        }
+
+        return Ok(());
+    }
+}
+
+impl Default for DecoderLimits {
+    fn default() -> DecoderLimits {
+        return DecoderLimits { max_depth: 128, ..DecoderLimits::unlimited() };
+    }
+}
@@ -0,0 +1,8 @@
+use decoder_limits::DecoderLimits;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecoderOptions {
+    pub limits: DecoderLimits,
+    pub widen: bool,
+    pub strict: bool
+}
@@ -1,11 +1,16 @@
 use std::collections;
 use std::io;
+use std::mem;
 
 use byteorder::{LittleEndian, WriteBytesExt};
 
-use super::{Event, Size};
+use super::{Event, EventBuf, Size};
 
+use canonical;
+use decoder::Decoder;
+use decoder_options::DecoderOptions;
 use encoder_error::{ErrorCode, EncoderError, EncoderResult};
+use encoder_options::EncoderOptions;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum StackSize { Streaming(u64, u64, u64), U64(u64) }
@@ -19,65 +24,56 @@ impl StackSize {
     }
 }
 
+enum Buffered { Event(EventBuf), Raw(Vec<u8>) }
+
+struct Pending {
+    start: usize,
+    count: u64,
+    modulo: u64,
+    required: u64,
+    expected: Option<u64>,
+    keyed: bool
+}
+
 pub struct Encoder<'a> {
     writer: &'a mut io::Write,
     dictionary: collections::HashMap<&'a str, usize>,
+    strings: &'a [&'a str],
     stack: Vec<StackSize>,
-    invalid_state: bool
+    invalid_state: bool,
+    options: EncoderOptions,
+    buffer: Vec<Buffered>,
+    pending: Vec<Pending>
 }
 
 impl<'a> Encoder<'a> {
     pub fn new(writer: &'a mut io::Write, dictionary: &'a [&'a str]) -> Encoder<'a> {
+        return Encoder::with_options(writer, dictionary, EncoderOptions::default());
+    }
+
+    pub fn buffered(writer: &'a mut io::Write, dictionary: &'a [&'a str]) -> Encoder<'a> {
+        return Encoder::with_options(writer, dictionary, EncoderOptions { buffered: true, ..EncoderOptions::default() });
+    }
+
+    pub fn with_options(writer: &'a mut io::Write, dictionary: &'a [&'a str], options: EncoderOptions) -> Encoder<'a> {
         let mut map = collections::HashMap::new();
 
         for (i, e) in dictionary.iter().enumerate() {
-            map.insert(*e, i);
+            map.entry(*e).or_insert(i);
         }
 
         return Encoder {
             writer: writer,
             dictionary: map,
+            strings: dictionary,
             stack: vec![StackSize::U64(1)],
-            invalid_state: false
+            invalid_state: false,
+            options: options,
+            buffer: Vec::new(),
+            pending: Vec::new()
         };
     }
 
-    #[inline]
-    fn write_length(&mut self, length: Size) -> EncoderResult<()> {
-        match length {
-            Size::U64(length) if length < 0xEF => {
-                try!(self.writer.write_u8(length as u8))
-            }
-            _ => panic!("Not implemented yet")
-        }
-
-        return Ok(());
-    }
-
-    #[inline]
-    fn write_string(&mut self, s: &str) -> EncoderResult<()> {
-        if let Some(i) = self.dictionary.get(s) {
-            if *i <= 0b01111111 {
-                try!(self.writer.write_u8(*i as u8 | 0b10000000));
-            } else {
-                panic!("Not implemented yet");
-            }
-
-            return Ok(());
-        }
-
-        if s.len() <= 0b00011111 {
-            try!(self.writer.write_u8(0b01100000 | s.len() as u8));
-        } else {
-            try!(self.writer.write_u8(0x09));
-            try!(self.write_length(Size::U64(s.len() as u64)));
-        }
-
-        try!(self.writer.write_all(s.as_bytes()));
-
-        return Ok(());
-    }
-
     #[inline]
     fn remove_one_from_stack(&mut self) -> EncoderResult<()> {
         let remaining = match self.stack.pop() {
@@ -100,124 +96,475 @@ impl<'a> Encoder<'a> {
         return Ok(());
     }
 
-    pub fn write(&mut self, event: &Event) -> EncoderResult<()> {
+    pub fn finish(self) -> EncoderResult<()> {
         if self.invalid_state {
             return Err(EncoderError::StreamError(ErrorCode::InvalidState));
         }
 
-        if event == &Event::End {
-            return match self.stack.pop() {
-                Some(StackSize::U64(0)) => Ok(()),
-                Some(StackSize::Streaming(n, m, r)) if n >= r && n % m == r => Ok(()),
-                _ => {
-                    self.invalid_state = true;
-                    Err(EncoderError::StreamError(ErrorCode::InvalidEnd))
-                }
-            };
+        if !self.pending.is_empty() || self.stack != [StackSize::U64(0)] {
+            return Err(EncoderError::StreamError(ErrorCode::MissingEnd));
+        }
+
+        try!(self.writer.flush());
+
+        return Ok(());
+    }
+
+    pub fn write_raw(&mut self, raw: &[u8]) -> EncoderResult<()> {
+        if self.invalid_state {
+            return Err(EncoderError::StreamError(ErrorCode::InvalidState));
+        }
+
+        if self.options.canonical && !self.is_canonical_value(raw) {
+            return Err(EncoderError::StreamError(ErrorCode::NonCanonical));
+        }
+
+        if !self.pending.is_empty() {
+            try!(self.count_pending());
+            self.buffer.push(Buffered::Raw(raw.to_vec()));
+
+            return Ok(());
         }
 
+        return self.write_raw_value(raw);
+    }
+
+    fn is_canonical_value(&self, raw: &[u8]) -> bool {
+        let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+        let mut decoder = Decoder::with_slice(raw, self.strings, options);
+
+        return match decoder.read_raw_value() {
+            Ok(value) => value.len() == raw.len(),
+            Err(_) => false
+        };
+    }
+
+    fn write_raw_value(&mut self, raw: &[u8]) -> EncoderResult<()> {
         try!(self.remove_one_from_stack());
+        try!(self.writer.write_all(raw));
 
-        match *event {
-            Event::Nil => {
-                try!(self.writer.write_u8(0x01))
-            }
-            Event::Boolean(false) => {
-                try!(self.writer.write_u8(0x02))
-            }
-            Event::Boolean(true) => {
-                try!(self.writer.write_u8(0x03))
-            }
-            Event::U8(v) => {
-                try!(self.writer.write_u8(0x10));
-                try!(self.writer.write_u8(v));
-            }
-            Event::U16(v) => {
-                try!(self.writer.write_u8(0x11));
-                try!(self.writer.write_u16::<LittleEndian>(v));
-            }
-            Event::U32(v) => {
-                try!(self.writer.write_u8(0x12));
-                try!(self.writer.write_u32::<LittleEndian>(v));
-            }
-            Event::U64(v) => {
-                try!(self.writer.write_u8(0x13));
-                try!(self.writer.write_u64::<LittleEndian>(v));
+        return Ok(());
+    }
+
+    pub fn write(&mut self, event: &Event) -> EncoderResult<()> {
+        if self.invalid_state {
+            return Err(EncoderError::StreamError(ErrorCode::InvalidState));
+        }
+
+        let normalized;
+
+        let event = if self.options.canonical {
+            normalized = canonical::canonicalize(event);
+            &normalized
+        } else if self.options.compact_numbers {
+            normalized = canonical::compact(event);
+            &normalized
+        } else {
+            event
+        };
+
+        if !self.pending.is_empty() || self.starts_buffering(event) {
+            return self.buffer_event(event);
+        }
+
+        return self.write_event(event);
+    }
+
+    fn starts_buffering(&self, event: &Event) -> bool {
+        if self.options.canonical {
+            return is_streaming_start(event) || is_keyed_start(event);
+        }
+
+        return self.options.buffered && is_streaming_start(event);
+    }
+
+    fn count_pending(&mut self) -> EncoderResult<()> {
+        let error = match self.pending.last_mut() {
+            Some(ref parent) if parent.expected == Some(parent.count) => ErrorCode::MissingEnd,
+            Some(parent) => {
+                parent.count += 1;
+
+                return Ok(());
             }
-            Event::I8(v) => {
-                try!(self.writer.write_u8(0x14));
-                try!(self.writer.write_i8(v));
+            None => match self.stack.last() {
+                Some(&StackSize::U64(0)) => ErrorCode::MissingEnd,
+                Some(_) => return Ok(()),
+                None => ErrorCode::EndOfStream
             }
-            Event::I16(v) => {
-                try!(self.writer.write_u8(0x15));
-                try!(self.writer.write_i16::<LittleEndian>(v));
+        };
+
+        self.invalid_state = true;
+
+        return Err(EncoderError::StreamError(error));
+    }
+
+    fn buffer_event(&mut self, event: &Event) -> EncoderResult<()> {
+        if *event == Event::End {
+            let container = match self.pending.pop() {
+                Some(container) => container,
+                None => {
+                    self.invalid_state = true;
+
+                    return Err(EncoderError::StreamError(ErrorCode::InvalidEnd));
+                }
+            };
+
+            let (n, m, r) = (container.count, container.modulo, container.required);
+
+            let complete = match container.expected {
+                Some(expected) => n == expected,
+                None => n >= r && (n - r) % m == 0
+            };
+
+            if !complete {
+                self.invalid_state = true;
+
+                return Err(EncoderError::StreamError(ErrorCode::InvalidEnd));
             }
-            Event::I32(v) => {
-                try!(self.writer.write_u8(0x16));
-                try!(self.writer.write_i32::<LittleEndian>(v));
+
+            if container.expected.is_none() {
+                let size = Size::U64((n - r) / m);
+
+                self.buffer[container.start] = Buffered::Event(match self.buffer[container.start] {
+                    Buffered::Event(EventBuf::StartArray(_)) => EventBuf::StartArray(size),
+                    Buffered::Event(EventBuf::StartStruct(_)) => EventBuf::StartStruct(size),
+                    Buffered::Event(EventBuf::StartMap(_)) => EventBuf::StartMap(size),
+                    _ => EventBuf::StartOpenStruct(size)
+                });
             }
-            Event::I64(v) => {
-                try!(self.writer.write_u8(0x17));
-                try!(self.writer.write_i64::<LittleEndian>(v));
+
+            if container.keyed {
+                try!(self.sort_entries(container.start, container.required as usize));
             }
-            Event::Fixnum(_) => {
-                panic!("Not implemented yet");
+
+            self.buffer.push(Buffered::Event(EventBuf::End));
+
+            if self.pending.is_empty() {
+                return self.flush_buffer();
             }
-            Event::F32(v) => {
-                try!(self.writer.write_u8(0x1A));
-                try!(self.writer.write_f32::<LittleEndian>(v));
+
+            return Ok(());
+        }
+
+        try!(self.count_pending());
+
+        let start = self.buffer.len();
+
+        if let Some((size, modulo, required)) = container(event) {
+            self.pending.push(Pending {
+                start: start,
+                count: 0,
+                modulo: modulo,
+                required: required,
+                expected: match size {
+                    Size::U64(size) => Some(modulo * size + required),
+                    Size::Streaming => None
+                },
+                keyed: self.options.canonical && is_keyed_start(event)
+            });
+        }
+
+        self.buffer.push(Buffered::Event(EventBuf::from(event.clone())));
+
+        return Ok(());
+    }
+
+    fn sort_entries(&mut self, start: usize, leading: usize) -> EncoderResult<()> {
+        let mut elements = Vec::new();
+        let mut depth = 0;
+
+        for item in self.buffer.split_off(start + 1) {
+            if depth == 0 {
+                elements.push(Vec::new());
             }
-            Event::F64(v) => {
-                try!(self.writer.write_u8(0x1B));
-                try!(self.writer.write_f64::<LittleEndian>(v));
+
+            match item {
+                Buffered::Event(EventBuf::End) => depth -= 1,
+                Buffered::Event(ref event) if container(&event.as_event()).is_some() => depth += 1,
+                _ => ()
             }
-            Event::Binary(v) => {
-                try!(self.writer.write_u8(0x08));
-                try!(self.write_length(Size::U64(v.len() as u64)));
-                try!(self.writer.write_all(v));
+
+            elements.last_mut().unwrap().push(item);
+        }
+
+        let mut elements = elements.into_iter();
+
+        for element in elements.by_ref().take(leading) {
+            self.buffer.extend(element);
+        }
+
+        let mut entries = Vec::new();
+
+        while let Some(key) = elements.next() {
+            let value = elements.next().unwrap_or_default();
+
+            entries.push((try!(self.encode_items(&key)), key, value));
+        }
+
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            self.invalid_state = true;
+
+            return Err(EncoderError::StreamError(ErrorCode::DuplicateKey));
+        }
+
+        for (_, key, value) in entries {
+            self.buffer.extend(key);
+            self.buffer.extend(value);
+        }
+
+        return Ok(());
+    }
+
+    fn encode_items(&self, items: &[Buffered]) -> EncoderResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        for item in items {
+            match *item {
+                Buffered::Event(EventBuf::End) => (),
+                Buffered::Event(ref event) => try!(write_value(&mut bytes, &self.dictionary, &event.as_event())),
+                Buffered::Raw(ref raw) => bytes.extend_from_slice(raw)
             }
-            Event::String(v) => try!(self.write_string(v)),
-            Event::StartArray(v) => {
-                match v {
-                    Size::U64(length) if length < 0b00001111 => {
-                        try!(self.writer.write_u8(0b00100000 | length as u8));
-                    }
-                    length => {
-                        try!(self.writer.write_u8(0x0A));
-                        try!(self.write_length(length));
-                    }
-                }
+        }
 
-                self.stack.push(StackSize::from_size(v, 1, 0));
+        return Ok(bytes);
+    }
+
+    fn flush_buffer(&mut self) -> EncoderResult<()> {
+        for item in mem::take(&mut self.buffer) {
+            let result = match item {
+                Buffered::Event(event) => self.write_event(&event.as_event()),
+                Buffered::Raw(raw) => self.write_raw_value(&raw)
+            };
+
+            if let Err(e) = result {
+                self.invalid_state = true;
+
+                return Err(e);
             }
-            Event::StartStruct(v) => {
-                try!(self.writer.write_u8(0x0B));
-                try!(self.write_length(v));
+        }
 
-                self.stack.push(StackSize::from_size(v, 1, 1));
+        return Ok(());
+    }
+
+    fn write_event(&mut self, event: &Event) -> EncoderResult<()> {
+        if event == &Event::End {
+            if self.stack.len() == 1 {
+                self.invalid_state = true;
+
+                return Err(EncoderError::StreamError(ErrorCode::InvalidEnd));
             }
-            Event::StartMap(v) => {
-                match v {
-                    Size::U64(length) if length < 0b00001111 => {
-                        try!(self.writer.write_u8(0b00110000 | length as u8));
-                    }
-                    length => {
-                        try!(self.writer.write_u8(0x0C));
-                        try!(self.write_length(length));
-                    }
+
+            return match self.stack.pop() {
+                Some(StackSize::U64(0)) => Ok(()),
+                Some(StackSize::Streaming(n, m, r)) if n >= r && (n - r) % m == 0 => {
+                    try!(self.writer.write_u8(0x00));
+
+                    Ok(())
+                }
+                _ => {
+                    self.invalid_state = true;
+                    Err(EncoderError::StreamError(ErrorCode::InvalidEnd))
                 }
+            };
+        }
 
-                self.stack.push(StackSize::from_size(v, 2, 0));
-            }
-            Event::StartOpenStruct(v) => {
-                try!(self.writer.write_u8(0x0D));
-                try!(self.write_length(v));
+        try!(self.remove_one_from_stack());
+        try!(write_value(self.writer, &self.dictionary, event));
 
-                self.stack.push(StackSize::from_size(v, 2, 1));
-            }
-            Event::End => unreachable!()
+        if let Some((size, modulo, required)) = container(event) {
+            self.stack.push(StackSize::from_size(size, modulo, required));
+        }
+
+        return Ok(());
+    }
+}
+
+#[inline]
+fn write_length(writer: &mut io::Write, length: Size) -> EncoderResult<()> {
+    match length {
+        Size::U64(length) if length < 0xEF => {
+            try!(writer.write_u8(length as u8))
+        }
+        Size::U64(length) if length <= 0xFF => {
+            try!(writer.write_u8(0xF1));
+            try!(writer.write_u8(length as u8));
+        }
+        Size::U64(length) if length <= 0xFFFF => {
+            try!(writer.write_u8(0xF2));
+            try!(writer.write_u16::<LittleEndian>(length as u16));
+        }
+        Size::U64(length) if length <= 0xFFFFFFFF => {
+            try!(writer.write_u8(0xF3));
+            try!(writer.write_u32::<LittleEndian>(length as u32));
+        }
+        Size::U64(length) => {
+            try!(writer.write_u8(0xF4));
+            try!(writer.write_u64::<LittleEndian>(length));
+        }
+        Size::Streaming => {
+            try!(writer.write_u8(0xF0))
+        }
+    }
+
+    return Ok(());
+}
+
+#[inline]
+fn write_string(writer: &mut io::Write, dictionary: &collections::HashMap<&str, usize>, s: &str) -> EncoderResult<()> {
+    if let Some(&i) = dictionary.get(s) {
+        if i <= 0b01111111 {
+            try!(writer.write_u8(i as u8 | 0b10000000));
+        } else if i <= 0xFF {
+            try!(writer.write_u8(0x09));
+            try!(writer.write_u8(0xF5));
+            try!(writer.write_u8(i as u8));
+        } else if i <= 0xFFFF {
+            try!(writer.write_u8(0x09));
+            try!(writer.write_u8(0xF6));
+            try!(writer.write_u16::<LittleEndian>(i as u16));
+        } else if i <= 0xFFFFFFFF {
+            try!(writer.write_u8(0x09));
+            try!(writer.write_u8(0xF7));
+            try!(writer.write_u32::<LittleEndian>(i as u32));
+        } else {
+            try!(writer.write_u8(0x09));
+            try!(writer.write_u8(0xF8));
+            try!(writer.write_u64::<LittleEndian>(i as u64));
         }
 
         return Ok(());
     }
+
+    if s.len() <= 0b00011111 {
+        try!(writer.write_u8(0b01100000 | s.len() as u8));
+    } else {
+        try!(writer.write_u8(0x09));
+        try!(write_length(writer, Size::U64(s.len() as u64)));
+    }
+
+    try!(writer.write_all(s.as_bytes()));
+
+    return Ok(());
+}
+
+fn write_value(writer: &mut io::Write, dictionary: &collections::HashMap<&str, usize>, event: &Event) -> EncoderResult<()> {
+    match *event {
+        Event::Nil => {
+            try!(writer.write_u8(0x01))
+        }
+        Event::Boolean(false) => {
+            try!(writer.write_u8(0x02))
+        }
+        Event::Boolean(true) => {
+            try!(writer.write_u8(0x03))
+        }
+        Event::U8(v) => {
+            try!(writer.write_u8(0x10));
+            try!(writer.write_u8(v));
+        }
+        Event::U16(v) => {
+            try!(writer.write_u8(0x11));
+            try!(writer.write_u16::<LittleEndian>(v));
+        }
+        Event::U32(v) => {
+            try!(writer.write_u8(0x12));
+            try!(writer.write_u32::<LittleEndian>(v));
+        }
+        Event::U64(v) => {
+            try!(writer.write_u8(0x13));
+            try!(writer.write_u64::<LittleEndian>(v));
+        }
+        Event::I8(v) => {
+            try!(writer.write_u8(0x14));
+            try!(writer.write_i8(v));
+        }
+        Event::I16(v) => {
+            try!(writer.write_u8(0x15));
+            try!(writer.write_i16::<LittleEndian>(v));
+        }
+        Event::I32(v) => {
+            try!(writer.write_u8(0x16));
+            try!(writer.write_i32::<LittleEndian>(v));
+        }
+        Event::I64(v) => {
+            try!(writer.write_u8(0x17));
+            try!(writer.write_i64::<LittleEndian>(v));
+        }
+        Event::Fixnum(v) => {
+            try!(writer.write_u8(0x18));
+            try!(write_length(writer, Size::U64(v.len() as u64)));
+            try!(writer.write_all(v));
+        }
+        Event::F32(v) => {
+            try!(writer.write_u8(0x1A));
+            try!(writer.write_f32::<LittleEndian>(v));
+        }
+        Event::F64(v) => {
+            try!(writer.write_u8(0x1B));
+            try!(writer.write_f64::<LittleEndian>(v));
+        }
+        Event::Binary(v) => {
+            try!(writer.write_u8(0x08));
+            try!(write_length(writer, Size::U64(v.len() as u64)));
+            try!(writer.write_all(v));
+        }
+        Event::String(v) => try!(write_string(writer, dictionary, v)),
+        Event::StartArray(v) => {
+            match v {
+                Size::U64(length) if length < 0b00001111 => {
+                    try!(writer.write_u8(0b00100000 | length as u8));
+                }
+                length => {
+                    try!(writer.write_u8(0x0A));
+                    try!(write_length(writer, length));
+                }
+            }
+        }
+        Event::StartStruct(v) => {
+            try!(writer.write_u8(0x0B));
+            try!(write_length(writer, v));
+        }
+        Event::StartMap(v) => {
+            match v {
+                Size::U64(length) if length < 0b00001111 => {
+                    try!(writer.write_u8(0b00110000 | length as u8));
+                }
+                length => {
+                    try!(writer.write_u8(0x0C));
+                    try!(write_length(writer, length));
+                }
+            }
+        }
+        Event::StartOpenStruct(v) => {
+            try!(writer.write_u8(0x0D));
+            try!(write_length(writer, v));
+        }
+        Event::End => unreachable!()
+    }
+
+    return Ok(());
+}
+
+fn container(event: &Event) -> Option<(Size, u64, u64)> {
+    return match *event {
+        Event::StartArray(size) => Some((size, 1, 0)),
+        Event::StartStruct(size) => Some((size, 1, 1)),
+        Event::StartMap(size) => Some((size, 2, 0)),
+        Event::StartOpenStruct(size) => Some((size, 2, 1)),
+        _ => None
+    };
+}
+
+fn is_keyed_start(event: &Event) -> bool {
+    return matches!(*event, Event::StartMap(_) | Event::StartOpenStruct(_));
+}
+
+fn is_streaming_start(event: &Event) -> bool {
+    return matches!(*event,
+                    Event::StartArray(Size::Streaming) |
+                    Event::StartStruct(Size::Streaming) |
+                    Event::StartMap(Size::Streaming) |
+                    Event::StartOpenStruct(Size::Streaming));
 }
@@ -1,20 +1,60 @@
+use std::error;
+use std::fmt;
 use std::io;
 
 use byteorder;
 
 #[derive(Debug, PartialEq)]
 pub enum ErrorCode {
+    DuplicateKey,
     EndOfStream,
     InvalidDictionaryIndex,
     InvalidEnd,
     InvalidState,
-    MissingEnd
+    MissingEnd,
+    NonCanonical
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match *self {
+            ErrorCode::DuplicateKey => "map or open struct contains the same key twice",
+            ErrorCode::EndOfStream => "wrote past the end of the stream",
+            ErrorCode::InvalidDictionaryIndex => "dictionary index out of range",
+            ErrorCode::InvalidEnd => "end of container in an invalid position",
+            ErrorCode::InvalidState => "encoder is in an invalid state after an earlier error",
+            ErrorCode::MissingEnd => "container is missing its end",
+            ErrorCode::NonCanonical => "raw value is not in canonical form"
+        };
+
+        return f.write_str(message);
+    }
 }
 
 #[derive(Debug)]
 pub enum EncoderError {
     StreamError(ErrorCode),
-    IoError(io::Error)
+    IoError(io::Error),
+    ApplicationError(String)
+}
+
+impl fmt::Display for EncoderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            EncoderError::StreamError(ref code) => write!(f, "{}", code),
+            EncoderError::IoError(ref error) => write!(f, "{}", error),
+            EncoderError::ApplicationError(ref message) => f.write_str(message)
+        };
+    }
+}
+
+impl error::Error for EncoderError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        return match *self {
+            EncoderError::IoError(ref error) => Some(error),
+            _ => None
+        };
+    }
 }
 
 impl From<byteorder::Error> for EncoderError {
@@ -33,9 +73,43 @@ impl PartialEq for EncoderError {
     fn eq(&self, other: &EncoderError) -> bool {
         return match (self, other) {
             (&EncoderError::StreamError(ref m0), &EncoderError::StreamError(ref m1)) => m0 == m1,
+            (&EncoderError::IoError(ref e0), &EncoderError::IoError(ref e1)) => e0.kind() == e1.kind(),
+            (&EncoderError::ApplicationError(ref m0), &EncoderError::ApplicationError(ref m1)) => m0 == m1,
             _ => false
         };
     }
 }
 
 pub type EncoderResult<T> = Result<T, EncoderError>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::io;
+
+    use super::{ErrorCode, EncoderError};
+
+    #[test]
+    fn displays_error() {
+        assert_eq!(EncoderError::StreamError(ErrorCode::MissingEnd).to_string(), "container is missing its end");
+        assert_eq!(EncoderError::IoError(io::Error::new(io::ErrorKind::Other, "oops")).to_string(), "oops");
+    }
+
+    #[test]
+    fn exposes_io_error_as_source() {
+        let error = EncoderError::IoError(io::Error::new(io::ErrorKind::Other, "oops"));
+
+        assert_eq!(error.source().map(|e| e.to_string()), Some("oops".to_string()));
+        assert!(EncoderError::StreamError(ErrorCode::InvalidEnd).source().is_none());
+    }
+
+    #[test]
+    fn compares_io_errors_by_kind() {
+        let e0 = EncoderError::IoError(io::Error::new(io::ErrorKind::WriteZero, "a"));
+        let e1 = EncoderError::IoError(io::Error::new(io::ErrorKind::WriteZero, "b"));
+        let e2 = EncoderError::IoError(io::Error::new(io::ErrorKind::Other, "a"));
+
+        assert_eq!(e0, e1);
+        assert!(e0 != e2);
+    }
+}
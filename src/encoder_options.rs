@@ -0,0 +1,6 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EncoderOptions {
+    pub buffered: bool,
+    pub compact_numbers: bool,
+    pub canonical: bool
+}
@@ -0,0 +1,156 @@
+//! Conversions between native integers and the `Fixnum` wire representation.
+//!
+//! A fixnum is a length-prefixed little-endian two's complement integer, written with the minimal
+//! length left by `trim`.
+
+use std::cmp;
+
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+
+pub fn from_i128(value: i128) -> Vec<u8> {
+    return trim(&value.to_le_bytes()).to_vec();
+}
+
+pub fn from_u128(value: u128) -> Vec<u8> {
+    let mut bytes = value.to_le_bytes().to_vec();
+
+    while bytes.last() == Some(&0x00) {
+        bytes.pop();
+    }
+
+    if let Some(&last) = bytes.last() {
+        if last & 0x80 == 0x80 {
+            bytes.push(0x00);
+        }
+    }
+
+    return bytes;
+}
+
+pub fn trim(bytes: &[u8]) -> &[u8] {
+    let mut length = bytes.len();
+
+    while length > 0 {
+        let redundant = match (bytes[length - 1], length) {
+            (0x00, 1) => true,
+            (0x00, _) => bytes[length - 2] & 0x80 == 0x00,
+            (0xFF, 1) => false,
+            (0xFF, _) => bytes[length - 2] & 0x80 == 0x80,
+            _ => false
+        };
+
+        if !redundant {
+            break;
+        }
+
+        length -= 1;
+    }
+
+    return &bytes[..length];
+}
+
+pub fn to_i128(bytes: &[u8]) -> DecoderResult<i128> {
+    let sign = match bytes.last() {
+        Some(&last) if last & 0x80 == 0x80 => 0xFF,
+        _ => 0x00
+    };
+
+    let (value, extension) = bytes.split_at(cmp::min(bytes.len(), 16));
+
+    if !extension.is_empty() && (value[15] & 0x80 != sign & 0x80 || extension.iter().any(|&b| b != sign)) {
+        return Err(DecoderError::StreamError(ErrorCode::Overflow));
+    }
+
+    let mut buffer = [sign; 16];
+
+    buffer[..value.len()].copy_from_slice(value);
+
+    return Ok(i128::from_le_bytes(buffer));
+}
+
+pub fn to_u128(bytes: &[u8]) -> DecoderResult<u128> {
+    if let Some(&last) = bytes.last() {
+        if last & 0x80 == 0x80 {
+            return Err(DecoderError::StreamError(ErrorCode::Overflow));
+        }
+    }
+
+    let (value, extension) = bytes.split_at(cmp::min(bytes.len(), 16));
+
+    if extension.iter().any(|&b| b != 0x00) {
+        return Err(DecoderError::StreamError(ErrorCode::Overflow));
+    }
+
+    let mut buffer = [0x00; 16];
+
+    buffer[..value.len()].copy_from_slice(value);
+
+    return Ok(u128::from_le_bytes(buffer));
+}
+
+#[cfg(test)]
+mod tests {
+    use decoder_error::{ErrorCode, DecoderError};
+
+    use super::{from_i128, from_u128, to_i128, to_u128, trim};
+
+    #[test]
+    fn encodes_minimal_i128() {
+        assert_eq!(from_i128(0), vec![]);
+        assert_eq!(from_i128(1), vec![0x01]);
+        assert_eq!(from_i128(-1), vec![0xFF]);
+        assert_eq!(from_i128(0x7F), vec![0x7F]);
+        assert_eq!(from_i128(0x80), vec![0x80, 0x00]);
+        assert_eq!(from_i128(-0x80), vec![0x80]);
+        assert_eq!(from_i128(-0x81), vec![0x7F, 0xFF]);
+        assert_eq!(from_i128(i128::MIN).len(), 16);
+    }
+
+    #[test]
+    fn encodes_minimal_u128() {
+        assert_eq!(from_u128(0), vec![]);
+        assert_eq!(from_u128(0x7F), vec![0x7F]);
+        assert_eq!(from_u128(0x80), vec![0x80, 0x00]);
+        assert_eq!(from_u128(u128::MAX).len(), 17);
+    }
+
+    #[test]
+    fn round_trips_i128() {
+        for &v in &[0, 1, -1, 0x7F, 0x80, -0x80, -0x81, 0x1234567890ABCDEF, i128::MIN, i128::MAX] {
+            assert_eq!(to_i128(&from_i128(v)), Ok(v));
+        }
+    }
+
+    #[test]
+    fn round_trips_u128() {
+        for &v in &[0, 1, 0x7F, 0x80, 0xFF, 0x1234567890ABCDEF, u128::MAX] {
+            assert_eq!(to_u128(&from_u128(v)), Ok(v));
+        }
+    }
+
+    #[test]
+    fn trims_sign_extension() {
+        assert_eq!(trim(&[0x00]), &[]);
+        assert_eq!(trim(&[0x01, 0x00, 0x00]), &[0x01]);
+        assert_eq!(trim(&[0x80, 0x00]), &[0x80, 0x00]);
+        assert_eq!(trim(&[0xFF, 0xFF]), &[0xFF]);
+        assert_eq!(trim(&[0x7F, 0xFF]), &[0x7F, 0xFF]);
+    }
+
+    #[test]
+    fn accepts_sign_extended_input() {
+        assert_eq!(to_i128(&[0xFF; 20]), Ok(-1));
+        assert_eq!(to_u128(&[0x01, 0x00, 0x00]), Ok(1));
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert_eq!(to_i128(&from_u128(u128::MAX)), Err(DecoderError::StreamError(ErrorCode::Overflow)));
+        assert_eq!(to_u128(&from_i128(-1)), Err(DecoderError::StreamError(ErrorCode::Overflow)));
+
+        let mut bytes = vec![0x00; 17];
+        bytes[16] = 0x01;
+
+        assert_eq!(to_u128(&bytes), Err(DecoderError::StreamError(ErrorCode::Overflow)));
+    }
+}
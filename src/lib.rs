@@ -1,14 +1,55 @@
 extern crate byteorder;
 extern crate rustc_serialize;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
 
 pub mod decoder;
 pub mod decoder_error;
+pub mod decoder_limits;
+pub mod decoder_options;
+pub mod push_decoder;
+pub mod read_decoder;
 
 pub mod encoder;
 pub mod encoder_error;
+pub mod encoder_options;
+
+pub mod canonical;
+pub mod fixnum;
+
+#[cfg(feature = "serde")]
+pub mod serde_decoder;
+#[cfg(feature = "serde")]
+pub mod serde_encoder;
+
+pub mod value;
+pub mod value_ref;
+
+pub mod visitor;
+
+mod path;
+mod rustc_decoder;
+mod rustc_encoder;
+mod stack;
+mod token;
 
 pub use decoder::Decoder;
+pub use decoder_limits::DecoderLimits;
+pub use decoder_options::DecoderOptions;
+pub use push_decoder::PushDecoder;
+pub use read_decoder::ReadDecoder;
 pub use encoder::Encoder;
+pub use encoder_options::EncoderOptions;
+pub use value::Value;
+pub use value_ref::ValueRef;
+pub use visitor::Visitor;
+#[cfg(feature = "serde")]
+pub use serde_decoder::{from_decoder, from_slice};
+#[cfg(feature = "serde")]
+pub use serde_encoder::{to_vec, to_writer};
 
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
 pub enum Size {
@@ -31,14 +72,118 @@ pub enum Event<'a> {
     End
 }
 
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+pub enum EventBuf {
+    Nil,
+    Boolean(bool),
+    U8(u8), U16(u16), U32(u32), U64(u64),
+    I8(i8), I16(i16), I32(i32), I64(i64), Fixnum(Vec<u8>),
+    F32(f32), F64(f64),
+    Binary(Vec<u8>),
+    String(String),
+    StartArray(Size),
+    StartStruct(Size),
+    StartMap(Size),
+    StartOpenStruct(Size),
+    End
+}
+
+impl<'a> Event<'a> {
+    pub fn widen(self) -> Event<'a> {
+        return match self {
+            Event::U8(v) => Event::U64(v as u64),
+            Event::U16(v) => Event::U64(v as u64),
+            Event::U32(v) => Event::U64(v as u64),
+            Event::I8(v) => Event::I64(v as i64),
+            Event::I16(v) => Event::I64(v as i64),
+            Event::I32(v) => Event::I64(v as i64),
+            Event::F32(v) => Event::F64(v as f64),
+            event => event
+        };
+    }
+}
+
+impl EventBuf {
+    pub fn widen(self) -> EventBuf {
+        return match self {
+            EventBuf::U8(v) => EventBuf::U64(v as u64),
+            EventBuf::U16(v) => EventBuf::U64(v as u64),
+            EventBuf::U32(v) => EventBuf::U64(v as u64),
+            EventBuf::I8(v) => EventBuf::I64(v as i64),
+            EventBuf::I16(v) => EventBuf::I64(v as i64),
+            EventBuf::I32(v) => EventBuf::I64(v as i64),
+            EventBuf::F32(v) => EventBuf::F64(v as f64),
+            event => event
+        };
+    }
+
+    pub fn as_event<'a>(&'a self) -> Event<'a> {
+        return match *self {
+            EventBuf::Nil => Event::Nil,
+            EventBuf::Boolean(v) => Event::Boolean(v),
+            EventBuf::U8(v) => Event::U8(v),
+            EventBuf::U16(v) => Event::U16(v),
+            EventBuf::U32(v) => Event::U32(v),
+            EventBuf::U64(v) => Event::U64(v),
+            EventBuf::I8(v) => Event::I8(v),
+            EventBuf::I16(v) => Event::I16(v),
+            EventBuf::I32(v) => Event::I32(v),
+            EventBuf::I64(v) => Event::I64(v),
+            EventBuf::Fixnum(ref v) => Event::Fixnum(v),
+            EventBuf::F32(v) => Event::F32(v),
+            EventBuf::F64(v) => Event::F64(v),
+            EventBuf::Binary(ref v) => Event::Binary(v),
+            EventBuf::String(ref v) => Event::String(v),
+            EventBuf::StartArray(v) => Event::StartArray(v),
+            EventBuf::StartStruct(v) => Event::StartStruct(v),
+            EventBuf::StartMap(v) => Event::StartMap(v),
+            EventBuf::StartOpenStruct(v) => Event::StartOpenStruct(v),
+            EventBuf::End => Event::End
+        };
+    }
+}
+
+impl<'a> From<Event<'a>> for EventBuf {
+    fn from(event: Event<'a>) -> EventBuf {
+        return match event {
+            Event::Nil => EventBuf::Nil,
+            Event::Boolean(v) => EventBuf::Boolean(v),
+            Event::U8(v) => EventBuf::U8(v),
+            Event::U16(v) => EventBuf::U16(v),
+            Event::U32(v) => EventBuf::U32(v),
+            Event::U64(v) => EventBuf::U64(v),
+            Event::I8(v) => EventBuf::I8(v),
+            Event::I16(v) => EventBuf::I16(v),
+            Event::I32(v) => EventBuf::I32(v),
+            Event::I64(v) => EventBuf::I64(v),
+            Event::Fixnum(v) => EventBuf::Fixnum(v.to_vec()),
+            Event::F32(v) => EventBuf::F32(v),
+            Event::F64(v) => EventBuf::F64(v),
+            Event::Binary(v) => EventBuf::Binary(v.to_vec()),
+            Event::String(v) => EventBuf::String(v.to_string()),
+            Event::StartArray(v) => EventBuf::StartArray(v),
+            Event::StartStruct(v) => EventBuf::StartStruct(v),
+            Event::StartMap(v) => EventBuf::StartMap(v),
+            Event::StartOpenStruct(v) => EventBuf::StartOpenStruct(v),
+            Event::End => EventBuf::End
+        };
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io;
 
     use super::Decoder;
+    use super::DecoderOptions;
     use super::Encoder;
+    use super::EncoderOptions;
+    use super::ReadDecoder;
+
+    use decoder_error::DecoderResult;
+    use encoder_error::{EncoderError, ErrorCode};
 
-    use super::{Event, Size};
+    use super::{Event, EventBuf, Size};
 
     macro_rules! basic_test {
         ($identifier:ident, $input:expr) => {
@@ -64,9 +209,46 @@ mod tests {
                 let mut cursor = io::Cursor::new(&*stream);
 
                 let decoder = Decoder::new(&mut cursor, &dictionary[..]);
-                let events: Vec<Event> = decoder.collect();
+                let events: Vec<Event> = decoder.collect::<DecoderResult<_>>().unwrap();
 
                 assert_eq!(events, $input);
+
+                let decoder = ReadDecoder::new(&*stream, &dictionary[..]);
+                let events: Vec<EventBuf> = decoder.collect::<DecoderResult<_>>().unwrap();
+
+                assert_eq!(events.iter().map(|e| e.as_event()).collect::<Vec<Event>>(), $input);
+            }
+        };
+    }
+
+    macro_rules! dictionary_test {
+        ($identifier:ident, $index:expr, $length:expr, $encoded_length:expr) => {
+            #[test]
+            fn $identifier() {
+                let words: Vec<String> = (0..$length).map(|i| format!("word{}", i)).collect();
+                let dictionary: Vec<&str> = words.iter().map(|s| &s[..]).collect();
+                let input = vec![Event::String(dictionary[$index])];
+
+                let mut cursor = io::Cursor::new(Vec::new());
+
+                {
+                    let mut encoder = Encoder::new(&mut cursor, &dictionary[..]);
+
+                    for event in input.iter() {
+                        encoder.write(event).unwrap();
+                    }
+                }
+
+                let stream = cursor.into_inner();
+
+                assert_eq!(stream.len(), $encoded_length);
+
+                let mut cursor = io::Cursor::new(&*stream);
+
+                let decoder = Decoder::new(&mut cursor, &dictionary[..]);
+                let events: Vec<Event> = decoder.collect::<DecoderResult<_>>().unwrap();
+
+                assert_eq!(events, input);
             }
         };
     }
@@ -76,11 +258,26 @@ mod tests {
     basic_test!(transcodes_true, vec![Event::Boolean(true)]);
     basic_test!(transcodes_binary, vec![Event::Binary(&vec![0x01, 0x02, 0x03, 0x04])]);
     basic_test!(transcodes_string, vec![Event::String("🍪")]);
+    basic_test!(transcodes_long_string, vec![Event::String(&"🍪".repeat(0x3C))]);
+    basic_test!(transcodes_u8_length_binary, vec![Event::Binary(&vec![0x55; 0xEF])]);
+    basic_test!(transcodes_u16_length_binary, vec![Event::Binary(&vec![0x55; 0x100])]);
+    basic_test!(transcodes_u32_length_binary, vec![Event::Binary(&vec![0x55; 0x10000])]);
     basic_test!(transcodes_dictionary_string, vec![Event::String("🍪")], vec!["🍪"]);
+    dictionary_test!(transcodes_dictionary_string_127, 0x7F, 0x80, 1);
+    dictionary_test!(transcodes_dictionary_string_128, 0x80, 0x81, 3);
+    dictionary_test!(transcodes_dictionary_string_255, 0xFF, 0x100, 3);
+    dictionary_test!(transcodes_dictionary_string_256, 0x100, 0x101, 4);
+    dictionary_test!(transcodes_dictionary_string_65535, 0xFFFF, 0x10000, 4);
+    dictionary_test!(transcodes_dictionary_string_65536, 0x10000, 0x10001, 6);
     basic_test!(transcodes_array, vec![Event::StartArray(Size::U64(1)), Event::Nil, Event::End]);
+    basic_test!(transcodes_streaming_array, vec![Event::StartArray(Size::Streaming), Event::Nil, Event::Nil, Event::End]);
+    basic_test!(transcodes_nested_streaming_array, vec![Event::StartArray(Size::Streaming), Event::StartArray(Size::Streaming), Event::End, Event::StartArray(Size::U64(1)), Event::Nil, Event::End, Event::End]);
     basic_test!(transcodes_struct, vec![Event::StartStruct(Size::U64(1)), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
+    basic_test!(transcodes_streaming_struct, vec![Event::StartStruct(Size::Streaming), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(transcodes_map, vec![Event::StartMap(Size::U64(1)), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
+    basic_test!(transcodes_streaming_map, vec![Event::StartMap(Size::Streaming), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(transcodes_open_struct, vec![Event::StartOpenStruct(Size::U64(1)), Event::String("🍪"), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
+    basic_test!(transcodes_streaming_open_struct, vec![Event::StartOpenStruct(Size::Streaming), Event::String("🍪"), Event::String("🍪"), Event::Boolean(false), Event::End], vec!["🍪"]);
     basic_test!(transcodes_u8, vec![Event::U8(0x50)]);
     basic_test!(transcodes_u16, vec![Event::U16(0x5150)]);
     basic_test!(transcodes_u32, vec![Event::U32(0x53525150)]);
@@ -89,6 +286,334 @@ mod tests {
     basic_test!(transcodes_i16, vec![Event::I16(0x5150)]);
     basic_test!(transcodes_i32, vec![Event::I32(0x53525150)]);
     basic_test!(transcodes_i64, vec![Event::I64(0x5756555453525150)]);
+    basic_test!(transcodes_fixnum, vec![Event::Fixnum(&vec![0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x5B, 0x5C, 0x5D, 0x5E, 0x5F, 0x60])]);
+    basic_test!(transcodes_zero_fixnum, vec![Event::Fixnum(&vec![])]);
     basic_test!(transcodes_f32, vec![Event::F32(1.0)]);
     basic_test!(transcodes_f64, vec![Event::F64(1.0)]);
+
+    #[test]
+    fn finishes_complete_streams() {
+        let dictionary: Vec<&'static str> = vec![];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+
+            encoder.write(&Event::StartArray(Size::Streaming)).unwrap();
+            encoder.write(&Event::Nil).unwrap();
+            encoder.write(&Event::End).unwrap();
+
+            assert_eq!(encoder.finish(), Ok(()));
+        }
+
+        assert_eq!(buffer, vec![0x0A, 0xF0, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn reports_unfinished_streams() {
+        let dictionary: Vec<&'static str> = vec![];
+        let mut buffer = Vec::new();
+
+        let encoder = Encoder::new(&mut buffer, &dictionary[..]);
+
+        assert_eq!(encoder.finish(), Err(EncoderError::StreamError(ErrorCode::MissingEnd)));
+
+        let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+
+        encoder.write(&Event::StartStruct(Size::U64(1))).unwrap();
+        encoder.write(&Event::String("point")).unwrap();
+        encoder.write(&Event::U8(1)).unwrap();
+
+        assert_eq!(encoder.finish(), Err(EncoderError::StreamError(ErrorCode::MissingEnd)));
+    }
+
+    #[test]
+    fn rejects_end_after_root() {
+        let dictionary: Vec<&'static str> = vec![];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+
+            encoder.write(&Event::Nil).unwrap();
+
+            assert_eq!(encoder.write(&Event::End), Err(EncoderError::StreamError(ErrorCode::InvalidEnd)));
+            assert_eq!(encoder.finish(), Err(EncoderError::StreamError(ErrorCode::InvalidState)));
+        }
+
+        assert_eq!(buffer, vec![0x01]);
+    }
+
+    #[test]
+    fn buffers_streaming_containers() {
+        let dictionary = vec!["point"];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::buffered(&mut buffer, &dictionary[..]);
+
+            encoder.write(&Event::StartArray(Size::Streaming)).unwrap();
+            encoder.write(&Event::StartStruct(Size::Streaming)).unwrap();
+            encoder.write(&Event::String("point")).unwrap();
+            encoder.write(&Event::U8(1)).unwrap();
+            encoder.write(&Event::End).unwrap();
+            encoder.write(&Event::StartArray(Size::U64(1))).unwrap();
+            encoder.write_raw(&[0x01]).unwrap();
+            encoder.write(&Event::End).unwrap();
+            encoder.write(&Event::StartMap(Size::Streaming)).unwrap();
+
+            for i in 0..16 {
+                encoder.write(&Event::U8(i)).unwrap();
+                encoder.write(&Event::Nil).unwrap();
+            }
+
+            encoder.write(&Event::End).unwrap();
+            encoder.write(&Event::End).unwrap();
+            encoder.finish().unwrap();
+        }
+
+        let mut expected = vec![0x23, 0x0B, 0x01, 0x80, 0x10, 0x01, 0x21, 0x01, 0x0C, 0x10];
+
+        for i in 0..16 {
+            expected.extend_from_slice(&[0x10, i, 0x01]);
+        }
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn rejects_incomplete_buffered_containers() {
+        let dictionary: Vec<&'static str> = vec![];
+        let mut buffer = Vec::new();
+
+        let mut encoder = Encoder::buffered(&mut buffer, &dictionary[..]);
+
+        encoder.write(&Event::StartMap(Size::Streaming)).unwrap();
+        encoder.write(&Event::Nil).unwrap();
+
+        assert_eq!(encoder.write(&Event::End), Err(EncoderError::StreamError(ErrorCode::InvalidEnd)));
+    }
+
+    #[test]
+    fn checks_sizes_while_buffering() {
+        let dictionary: Vec<&'static str> = vec![];
+        let mut buffer = Vec::new();
+
+        {
+            let options = EncoderOptions { canonical: true, ..EncoderOptions::default() };
+            let mut encoder = Encoder::with_options(&mut buffer, &dictionary[..], options);
+
+            encoder.write(&Event::StartMap(Size::U64(1))).unwrap();
+            encoder.write(&Event::String("b")).unwrap();
+            encoder.write(&Event::Nil).unwrap();
+
+            assert_eq!(encoder.write(&Event::String("a")), Err(EncoderError::StreamError(ErrorCode::MissingEnd)));
+            assert_eq!(encoder.write(&Event::End), Err(EncoderError::StreamError(ErrorCode::InvalidState)));
+        }
+
+        {
+            let mut encoder = Encoder::buffered(&mut buffer, &dictionary[..]);
+
+            encoder.write(&Event::StartArray(Size::Streaming)).unwrap();
+            encoder.write(&Event::StartArray(Size::U64(2))).unwrap();
+            encoder.write(&Event::Nil).unwrap();
+
+            assert_eq!(encoder.write(&Event::End), Err(EncoderError::StreamError(ErrorCode::InvalidEnd)));
+        }
+
+        {
+            let mut encoder = Encoder::buffered(&mut buffer, &dictionary[..]);
+
+            encoder.write(&Event::Nil).unwrap();
+
+            assert_eq!(encoder.write(&Event::StartArray(Size::Streaming)), Err(EncoderError::StreamError(ErrorCode::MissingEnd)));
+        }
+
+        assert_eq!(buffer, vec![0x01]);
+    }
+
+    #[test]
+    fn compacts_and_widens_numbers() {
+        let dictionary: Vec<&'static str> = vec![];
+        let input = vec![
+            Event::StartArray(Size::U64(6)),
+            Event::U64(5), Event::U32(0x1000), Event::I64(-1), Event::I32(-0x10000), Event::F64(1.5), Event::F64(0.1),
+            Event::End
+        ];
+
+        let mut buffer = Vec::new();
+
+        {
+            let options = EncoderOptions { compact_numbers: true, ..EncoderOptions::default() };
+            let mut encoder = Encoder::with_options(&mut buffer, &dictionary[..], options);
+
+            for event in input.iter() {
+                encoder.write(event).unwrap();
+            }
+
+            encoder.finish().unwrap();
+        }
+
+        assert_eq!(&buffer[..13], &[0x26, 0x10, 0x05, 0x11, 0x00, 0x10, 0x14, 0xFF, 0x16, 0x00, 0x00, 0xFF, 0xFF]);
+        assert_eq!(&buffer[13..18], &[0x1A, 0x00, 0x00, 0xC0, 0x3F]);
+        assert_eq!(buffer[18], 0x1B);
+
+        let options = DecoderOptions { widen: true, ..DecoderOptions::default() };
+        let expected = vec![
+            Event::StartArray(Size::U64(6)),
+            Event::U64(5), Event::U64(0x1000), Event::I64(-1), Event::I64(-0x10000), Event::F64(1.5), Event::F64(0.1),
+            Event::End
+        ];
+
+        let mut cursor = io::Cursor::new(&*buffer);
+        let decoder = Decoder::with_options(&mut cursor, &dictionary[..], options);
+
+        assert_eq!(decoder.collect::<DecoderResult<Vec<Event>>>().unwrap(), expected);
+
+        let decoder = ReadDecoder::with_options(&buffer[..], &dictionary[..], options).unwrap();
+        let events = decoder.collect::<DecoderResult<Vec<EventBuf>>>().unwrap();
+
+        assert_eq!(events.iter().map(|e| e.as_event()).collect::<Vec<Event>>(), expected);
+    }
+
+    #[test]
+    fn encodes_canonical_form() {
+        let dictionary = vec!["b"];
+        let inputs = vec![
+            vec![
+                Event::StartMap(Size::Streaming),
+                Event::String("c"), Event::U64(1),
+                Event::String("b"), Event::F64(f64::NAN),
+                Event::String("a"), Event::StartArray(Size::Streaming), Event::I32(-1), Event::End,
+                Event::End
+            ],
+            vec![
+                Event::StartMap(Size::U64(3)),
+                Event::String("a"), Event::StartArray(Size::U64(1)), Event::I8(-1), Event::End,
+                Event::String("b"), Event::F32(f32::from_bits(0x7FC00001)),
+                Event::String("c"), Event::U8(1),
+                Event::End
+            ]
+        ];
+
+        let expected = vec![0x33, 0x61, 0x61, 0x21, 0x14, 0xFF, 0x61, 0x63, 0x10, 0x01, 0x80, 0x1A, 0x00, 0x00, 0xC0, 0x7F];
+
+        for input in inputs.iter() {
+            let mut buffer = Vec::new();
+
+            {
+                let options = EncoderOptions { canonical: true, ..EncoderOptions::default() };
+                let mut encoder = Encoder::with_options(&mut buffer, &dictionary[..], options);
+
+                for event in input.iter() {
+                    encoder.write(event).unwrap();
+                }
+
+                encoder.finish().unwrap();
+            }
+
+            assert_eq!(buffer, expected);
+
+            let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+            let mut cursor = io::Cursor::new(&*buffer);
+            let decoder = Decoder::with_options(&mut cursor, &dictionary[..], options);
+
+            assert_eq!(decoder.collect::<DecoderResult<Vec<Event>>>().unwrap().len(), 10);
+        }
+    }
+
+    #[test]
+    fn validates_canonical_raw_values() {
+        let dictionary = vec!["a"];
+        let mut buffer = Vec::new();
+
+        {
+            let options = EncoderOptions { canonical: true, ..EncoderOptions::default() };
+            let mut encoder = Encoder::with_options(&mut buffer, &dictionary[..], options);
+
+            encoder.write(&Event::StartArray(Size::U64(2))).unwrap();
+
+            for raw in [&[0x61, 0x61][..], &[0x11, 0x07, 0x00], &[0x32, 0x61, 0x62, 0x01, 0x61, 0x61, 0x01], &[0x01, 0x01]].iter() {
+                assert_eq!(encoder.write_raw(raw), Err(EncoderError::StreamError(ErrorCode::NonCanonical)));
+            }
+
+            encoder.write_raw(&[0x31, 0x80, 0x01]).unwrap();
+            encoder.write_raw(&[0x10, 0x07]).unwrap();
+            encoder.write(&Event::End).unwrap();
+            encoder.finish().unwrap();
+        }
+
+        assert_eq!(buffer, vec![0x22, 0x31, 0x80, 0x01, 0x10, 0x07]);
+    }
+
+    #[test]
+    fn rejects_duplicate_canonical_keys() {
+        let dictionary: Vec<&'static str> = vec![];
+        let mut buffer = Vec::new();
+        let options = EncoderOptions { canonical: true, ..EncoderOptions::default() };
+        let mut encoder = Encoder::with_options(&mut buffer, &dictionary[..], options);
+
+        encoder.write(&Event::StartOpenStruct(Size::U64(2))).unwrap();
+        encoder.write(&Event::String("Point")).unwrap();
+        encoder.write(&Event::String("x")).unwrap();
+        encoder.write(&Event::U8(1)).unwrap();
+        encoder.write(&Event::String("x")).unwrap();
+        encoder.write(&Event::U8(2)).unwrap();
+
+        assert_eq!(encoder.write(&Event::End).unwrap_err(), EncoderError::StreamError(ErrorCode::DuplicateKey));
+        assert_eq!(encoder.finish().unwrap_err(), EncoderError::StreamError(ErrorCode::InvalidState));
+    }
+
+    #[test]
+    fn passes_raw_values_through() {
+        let dictionary = vec!["🍪"];
+        let input = vec![
+            Event::StartMap(Size::U64(2)),
+            Event::String("🍪"), Event::StartArray(Size::Streaming), Event::U16(0x5150), Event::End,
+            Event::String("id"), Event::Binary(&[0x01, 0x02]),
+            Event::End
+        ];
+
+        let mut cursor = io::Cursor::new(Vec::new());
+
+        {
+            let mut encoder = Encoder::new(&mut cursor, &dictionary[..]);
+
+            for event in input.iter() {
+                encoder.write(event).unwrap();
+            }
+        }
+
+        let stream = cursor.into_inner();
+
+        let mut cursor = io::Cursor::new(&*stream);
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.read(), Ok(Some(Event::StartMap(Size::U64(2)))));
+
+        let key = decoder.read_raw_value().unwrap();
+        let value = decoder.read_raw_value().unwrap();
+
+        assert_eq!(key, &[0x80]);
+        assert_eq!(value, &[0x0A, 0xF0, 0x11, 0x50, 0x51, 0x00]);
+
+        let mut cursor = io::Cursor::new(Vec::new());
+
+        {
+            let mut encoder = Encoder::new(&mut cursor, &dictionary[..]);
+
+            encoder.write(&Event::StartArray(Size::U64(2))).unwrap();
+            encoder.write_raw(value).unwrap();
+            encoder.write_raw(decoder.read_raw_value().unwrap()).unwrap();
+            encoder.write(&Event::End).unwrap();
+        }
+
+        let stream = cursor.into_inner();
+
+        let mut cursor = io::Cursor::new(&*stream);
+        let decoder = Decoder::new(&mut cursor, &dictionary[..]);
+        let events: Vec<Event> = decoder.collect::<DecoderResult<_>>().unwrap();
+
+        assert_eq!(events, vec![Event::StartArray(Size::U64(2)), Event::StartArray(Size::Streaming), Event::U16(0x5150), Event::End, Event::String("id"), Event::End]);
+    }
 }
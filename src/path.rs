@@ -0,0 +1,147 @@
+use std::fmt;
+
+use super::Event;
+
+use decoder_error::{DecoderError, Location};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Container { Root, Array, Struct, Map, OpenStruct }
+
+#[derive(Clone)]
+struct Frame<K> {
+    container: Container,
+    index: usize,
+    key: Option<K>
+}
+
+#[derive(Clone)]
+pub struct Path<K> {
+    frames: Vec<Frame<K>>
+}
+
+impl<K: AsRef<str>> Path<K> {
+    pub fn new() -> Path<K> {
+        return Path { frames: vec![Frame { container: Container::Root, index: 0, key: None }] };
+    }
+
+    pub fn start(&mut self, container: Container) {
+        self.frames.push(Frame { container: container, index: 0, key: None });
+    }
+
+    pub fn end(&mut self) {
+        self.frames.pop();
+        self.advance(|| None);
+    }
+
+    pub fn discard(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn track<'e, F: FnOnce(&'e str) -> K>(&mut self, event: &Event<'e>, key: F) {
+        match *event {
+            Event::StartArray(_) => self.start(Container::Array),
+            Event::StartStruct(_) => self.start(Container::Struct),
+            Event::StartMap(_) => self.start(Container::Map),
+            Event::StartOpenStruct(_) => self.start(Container::OpenStruct),
+            Event::End => self.end(),
+            Event::String(s) => self.advance(|| Some(key(s))),
+            _ => self.advance(|| None)
+        }
+    }
+
+    pub fn locate(&self, offset: u64, error: DecoderError) -> DecoderError {
+        return DecoderError::LocatedError(Location { offset: offset, path: self.to_string() }, Box::new(error));
+    }
+
+    pub fn advance<F: FnOnce() -> Option<K>>(&mut self, key: F) {
+        if let Some(frame) = self.frames.last_mut() {
+            if frame.is_key() {
+                frame.key = key();
+            }
+
+            frame.index += 1;
+        }
+    }
+}
+
+impl<K> Frame<K> {
+    fn is_key(&self) -> bool {
+        return match self.container {
+            Container::Map => self.index % 2 == 0,
+            Container::OpenStruct => self.index % 2 == 1,
+            _ => false
+        };
+    }
+}
+
+impl<K: AsRef<str>> fmt::Display for Path<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for frame in self.frames.iter() {
+            match (frame.container, frame.index) {
+                (Container::Root, _) => (),
+                (Container::Array, i) => try!(write!(f, "[{}]", i)),
+                (Container::Struct, 0) | (Container::OpenStruct, 0) => try!(write!(f, ".<name>")),
+                (Container::Struct, i) => try!(write!(f, "[{}]", i - 1)),
+                (Container::Map, i) | (Container::OpenStruct, i) => {
+                    let pair = match frame.container {
+                        Container::OpenStruct => (i - 1) / 2,
+                        _ => i / 2
+                    };
+
+                    match frame.key {
+                        _ if frame.is_key() => try!(write!(f, "{{{}}}", pair)),
+                        Some(ref key) => try!(write!(f, ".{}", key.as_ref())),
+                        None => try!(write!(f, "[{}]", pair))
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Container, Path};
+
+    #[test]
+    fn formats_nested_path() {
+        let mut path: Path<&str> = Path::new();
+
+        assert_eq!(path.to_string(), "");
+
+        path.start(Container::Array);
+        path.advance(|| None);
+        path.start(Container::OpenStruct);
+
+        assert_eq!(path.to_string(), "[1].<name>");
+
+        path.advance(|| None);
+        path.advance(|| Some("users"));
+        path.start(Container::Array);
+        path.advance(|| None);
+        path.advance(|| None);
+        path.start(Container::Map);
+
+        assert_eq!(path.to_string(), "[1].users[2]{0}");
+
+        path.advance(|| Some("name"));
+
+        assert_eq!(path.to_string(), "[1].users[2].name");
+
+        path.end();
+
+        assert_eq!(path.to_string(), "[1].users[3]");
+
+        path.end();
+
+        assert_eq!(path.to_string(), "[1]{1}");
+
+        path.advance(|| None);
+        path.start(Container::Struct);
+        path.advance(|| None);
+
+        assert_eq!(path.to_string(), "[1][1][0]");
+    }
+}
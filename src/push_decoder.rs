@@ -0,0 +1,262 @@
+use super::{EventBuf, Size};
+
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use decoder_limits::DecoderLimits;
+use decoder_options::DecoderOptions;
+use path::Path;
+use stack::StackSize;
+use token;
+
+pub struct PushDecoder<'a> {
+    buffer: Vec<u8>,
+    position: usize,
+    offset: u64,
+    dictionary: &'a [&'a str],
+    stack: Vec<StackSize>,
+    path: Path<String>,
+    options: DecoderOptions
+}
+
+impl<'a> PushDecoder<'a> {
+    pub fn new(dictionary: &'a [&'a str]) -> PushDecoder<'a> {
+        return PushDecoder::with_limits(dictionary, DecoderLimits::default());
+    }
+
+    pub fn with_limits(dictionary: &'a [&'a str], limits: DecoderLimits) -> PushDecoder<'a> {
+        return PushDecoder {
+            buffer: Vec::new(),
+            position: 0,
+            offset: 0,
+            dictionary: dictionary,
+            stack: vec![StackSize::U64(1)],
+            path: Path::new(),
+            options: DecoderOptions { limits: limits, ..DecoderOptions::default() }
+        };
+    }
+
+    pub fn with_options(dictionary: &'a [&'a str], options: DecoderOptions) -> DecoderResult<PushDecoder<'a>> {
+        if options.strict {
+            return Err(DecoderError::StreamError(ErrorCode::Unsupported));
+        }
+
+        let mut decoder = PushDecoder::with_limits(dictionary, options.limits);
+
+        decoder.options = options;
+
+        return Ok(decoder);
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        if self.position > 0 {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+        }
+
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn buffered(&self) -> usize {
+        return self.buffer.len() - self.position;
+    }
+
+    pub fn read(&mut self) -> DecoderResult<Option<EventBuf>> {
+        return match self.read_event() {
+            Ok(result) => {
+                if let Some(ref event) = result {
+                    self.path.track(&event.as_event(), |s| s.to_string());
+                }
+
+                if self.options.widen {
+                    return Ok(result.map(EventBuf::widen));
+                }
+
+                Ok(result)
+            }
+            Err(e) => {
+                if e.code() == Some(&ErrorCode::NeedMoreData) {
+                    return Err(e);
+                }
+
+                Err(self.path.locate(self.offset, e))
+            }
+        };
+    }
+
+    fn read_event(&mut self) -> DecoderResult<Option<EventBuf>> {
+        let remaining = match self.stack.last() {
+            Some(&remaining) => remaining,
+            None => {
+                return Err(DecoderError::StreamError(ErrorCode::EndOfStream));
+            }
+        };
+
+        if remaining == StackSize::U64(0) {
+            self.stack.pop();
+
+            if self.stack.len() == 0 {
+                return Ok(None)
+            } else {
+                return Ok(Some(EventBuf::End))
+            }
+        }
+
+        let (event, consumed) = {
+            let mut input = &self.buffer[self.position..];
+            let length = input.len();
+            let event = EventBuf::from(try!(token::parse(&mut input, self.dictionary, &self.options.limits)));
+
+            (event, length - input.len())
+        };
+
+        if event == EventBuf::End && !remaining.accepts_end() {
+            return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+        }
+
+        let parent = match event {
+            EventBuf::End => None,
+            _ => Some(try!(remaining.remove_one()))
+        };
+
+        let container = match event {
+            EventBuf::StartArray(size) => Some(try!(self.container(size, 1, 0))),
+            EventBuf::StartStruct(size) => Some(try!(self.container(size, 1, 1))),
+            EventBuf::StartMap(size) => Some(try!(self.container(size, 2, 0))),
+            EventBuf::StartOpenStruct(size) => Some(try!(self.container(size, 2, 1))),
+            _ => None
+        };
+
+        try!(self.options.limits.check_size(self.offset + consumed as u64));
+
+        self.position += consumed;
+        self.offset += consumed as u64;
+        self.stack.pop();
+
+        if let Some(parent) = parent {
+            self.stack.push(parent);
+        }
+
+        if let Some(container) = container {
+            self.stack.push(container);
+        }
+
+        return Ok(Some(event));
+    }
+
+    #[inline]
+    fn container(&self, size: Size, modulo: usize, required: usize) -> DecoderResult<StackSize> {
+        try!(self.options.limits.check_depth(self.stack.len()));
+
+        return StackSize::from_size(size, modulo, required, &self.options.limits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use ::{Encoder, Event, EventBuf, Size};
+
+    use decoder_error::{ErrorCode, DecoderError};
+    use decoder_limits::DecoderLimits;
+    use decoder_options::DecoderOptions;
+
+    use super::PushDecoder;
+
+    #[test]
+    fn decodes_byte_by_byte() {
+        let dictionary = vec!["🍪"];
+        let input = vec![
+            Event::StartOpenStruct(Size::Streaming),
+            Event::String("🍪"),
+            Event::String("name"),
+            Event::StartArray(Size::U64(2)),
+            Event::U32(0x53525150),
+            Event::Binary(&[0x01, 0x02, 0x03]),
+            Event::End,
+            Event::End
+        ];
+
+        let mut cursor = io::Cursor::new(Vec::new());
+
+        {
+            let mut encoder = Encoder::new(&mut cursor, &dictionary[..]);
+
+            for event in input.iter() {
+                encoder.write(event).unwrap();
+            }
+        }
+
+        let stream = cursor.into_inner();
+
+        let mut decoder = PushDecoder::new(&dictionary[..]);
+        let mut events = Vec::new();
+
+        for byte in stream.iter() {
+            decoder.feed(&[*byte]);
+
+            loop {
+                match decoder.read() {
+                    Ok(Some(event)) => events.push(event),
+                    Ok(None) => break,
+                    Err(DecoderError::StreamError(ErrorCode::NeedMoreData)) => break,
+                    Err(e) => panic!("{:?}", e)
+                }
+            }
+        }
+
+        assert_eq!(events.iter().map(|e| e.as_event()).collect::<Vec<Event>>(), input);
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn keeps_partial_token() {
+        let dictionary: Vec<&'static str> = vec![];
+
+        let mut decoder = PushDecoder::new(&dictionary[..]);
+
+        decoder.feed(&[0x21, 0x11, 0x50]);
+
+        assert_eq!(decoder.read(), Ok(Some(EventBuf::StartArray(Size::U64(1)))));
+        assert_eq!(decoder.read(), Err(DecoderError::StreamError(ErrorCode::NeedMoreData)));
+        assert_eq!(decoder.buffered(), 2);
+
+        decoder.feed(&[0x51]);
+
+        assert_eq!(decoder.read(), Ok(Some(EventBuf::U16(0x5150))));
+        assert_eq!(decoder.read(), Ok(Some(EventBuf::End)));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn rejects_invalid_end() {
+        let dictionary: Vec<&'static str> = vec![];
+
+        let mut decoder = PushDecoder::new(&dictionary[..]);
+
+        decoder.feed(&[0x0C, 0xF0, 0x01, 0x00]);
+
+        assert_eq!(decoder.read(), Ok(Some(EventBuf::StartMap(Size::Streaming))));
+        assert_eq!(decoder.read(), Ok(Some(EventBuf::Nil)));
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::InvalidEnd));
+    }
+
+    #[test]
+    fn rejects_long_payload_before_buffering_it() {
+        let dictionary: Vec<&'static str> = vec![];
+
+        let limits = DecoderLimits { max_length: 0x1000, ..DecoderLimits::unlimited() };
+        let mut decoder = PushDecoder::with_limits(&dictionary[..], limits);
+
+        decoder.feed(&[0x09, 0xF3, 0x00, 0x00, 0x00, 0x40]);
+
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::LengthLimitExceeded));
+    }
+
+    #[test]
+    fn rejects_strict_option() {
+        let dictionary: Vec<&'static str> = vec![];
+        let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+
+        assert_eq!(PushDecoder::with_options(&dictionary[..], options).err().unwrap().code(), Some(&ErrorCode::Unsupported));
+    }
+}
@@ -0,0 +1,288 @@
+use std::io;
+use std::io::Read;
+
+use super::{Event, EventBuf, Size};
+
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use decoder_limits::DecoderLimits;
+use decoder_options::DecoderOptions;
+use path::Path;
+use stack::StackSize;
+use token;
+
+struct CountingReader<R: io::Read> {
+    inner: R,
+    count: u64
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = try!(self.inner.read(buf));
+
+        self.count += length as u64;
+
+        return Ok(length);
+    }
+}
+
+pub struct ReadDecoder<'a, R: io::Read> {
+    reader: CountingReader<R>,
+    dictionary: &'a [&'a str],
+    stack: Vec<StackSize>,
+    path: Path<String>,
+    options: DecoderOptions,
+    failed: bool
+}
+
+impl<'a, R: io::Read> ReadDecoder<'a, R> {
+    pub fn new(reader: R, dictionary: &'a [&'a str]) -> ReadDecoder<'a, R> {
+        return ReadDecoder::with_limits(reader, dictionary, DecoderLimits::default());
+    }
+
+    pub fn with_limits(reader: R, dictionary: &'a [&'a str], limits: DecoderLimits) -> ReadDecoder<'a, R> {
+        return ReadDecoder {
+            reader: CountingReader { inner: reader, count: 0 },
+            dictionary: dictionary,
+            stack: vec![StackSize::U64(1)],
+            path: Path::new(),
+            options: DecoderOptions { limits: limits, ..DecoderOptions::default() },
+            failed: false
+        };
+    }
+
+    pub fn with_options(reader: R, dictionary: &'a [&'a str], options: DecoderOptions) -> DecoderResult<ReadDecoder<'a, R>> {
+        if options.strict {
+            return Err(DecoderError::StreamError(ErrorCode::Unsupported));
+        }
+
+        let mut decoder = ReadDecoder::with_limits(reader, dictionary, options.limits);
+
+        decoder.options = options;
+
+        return Ok(decoder);
+    }
+
+    pub fn into_inner(self) -> R {
+        return self.reader.inner;
+    }
+
+    #[inline]
+    fn read_binary_data(&mut self, length: u64) -> DecoderResult<Vec<u8>> {
+        try!(self.options.limits.check_length(length));
+        try!(self.options.limits.check_size(self.reader.count.saturating_add(length)));
+
+        let mut buffer = Vec::new();
+
+        try!((&mut self.reader).take(length).read_to_end(&mut buffer));
+
+        if (buffer.len() as u64) < length {
+            return Err(DecoderError::StreamError(ErrorCode::UnexpectedEOF));
+        }
+
+        return Ok(buffer);
+    }
+
+    #[inline]
+    fn read_string_data(&mut self, length: u64) -> DecoderResult<String> {
+        let buffer = try!(self.read_binary_data(length));
+
+        return match String::from_utf8(buffer) {
+            Ok(s) => Ok(s),
+            Err(_) => Err(DecoderError::StreamError(ErrorCode::InvalidUTF8))
+        };
+    }
+
+    #[inline]
+    fn push_stack(&mut self, remaining: StackSize) -> DecoderResult<()> {
+        self.stack.push(try!(remaining.remove_one()));
+
+        return Ok(());
+    }
+
+    #[inline]
+    fn push_container(&mut self, remaining: StackSize, size: Size, modulo: usize, required: usize) -> DecoderResult<()> {
+        try!(self.options.limits.check_depth(self.stack.len() + 1));
+
+        let container = try!(StackSize::from_size(size, modulo, required, &self.options.limits));
+
+        try!(self.push_stack(remaining));
+
+        self.stack.push(container);
+
+        return Ok(());
+    }
+
+    pub fn read(&mut self) -> DecoderResult<Option<EventBuf>> {
+        let offset = self.reader.count;
+
+        return match self.read_checked() {
+            Ok(result) => {
+                if let Some(ref event) = result {
+                    self.path.track(&event.as_event(), |s| s.to_string());
+                }
+
+                if self.options.widen {
+                    return Ok(result.map(EventBuf::widen));
+                }
+
+                Ok(result)
+            }
+            Err(e) => Err(self.path.locate(offset, e))
+        };
+    }
+
+    fn read_checked(&mut self) -> DecoderResult<Option<EventBuf>> {
+        let result = try!(self.read_event());
+
+        try!(self.options.limits.check_size(self.reader.count));
+
+        return Ok(result);
+    }
+
+    fn read_event(&mut self) -> DecoderResult<Option<EventBuf>> {
+        let remaining = match self.stack.pop() {
+            Some(remaining) => remaining,
+            None => {
+                return Err(DecoderError::StreamError(ErrorCode::EndOfStream));
+            }
+        };
+
+        if remaining == StackSize::U64(0) {
+            if self.stack.len() == 0 {
+                return Ok(None);
+            }
+
+            return Ok(Some(EventBuf::End));
+        }
+
+        let result = match try!(token::read_token(&mut self.reader, false)) {
+            token::Token::Event(Event::End) => {
+                if remaining.accepts_end() {
+                    return Ok(Some(EventBuf::End));
+                }
+
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+            }
+            token::Token::Event(event) => EventBuf::from(event),
+            token::Token::Binary(length) => EventBuf::Binary(try!(self.read_binary_data(length))),
+            token::Token::Fixnum(length) => EventBuf::Fixnum(try!(self.read_binary_data(length))),
+            token::Token::String(length) => EventBuf::String(try!(self.read_string_data(length))),
+            token::Token::Dictionary(index) => EventBuf::String(try!(token::read_dictionary(self.dictionary, index)).to_string())
+        };
+
+        match token::container(&result.as_event()) {
+            Some((size, modulo, required)) => try!(self.push_container(remaining, size, modulo, required)),
+            None => try!(self.push_stack(remaining))
+        }
+
+        return Ok(Some(result));
+    }
+}
+
+impl<'a, R: io::Read> Iterator for ReadDecoder<'a, R> {
+    type Item = DecoderResult<EventBuf>;
+
+    fn next(&mut self) -> Option<DecoderResult<EventBuf>> {
+        if self.failed {
+            return None;
+        }
+
+        return match self.read() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.failed = true;
+
+                Some(Err(e))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp;
+    use std::io;
+
+    use ::{EventBuf, Size};
+
+    use decoder_error::{ErrorCode, DecoderResult};
+    use decoder_limits::DecoderLimits;
+    use decoder_options::DecoderOptions;
+
+    use super::ReadDecoder;
+
+    struct TrickleReader<'a> {
+        data: &'a [u8]
+    }
+
+    impl<'a> io::Read for TrickleReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let length = cmp::min(1, cmp::min(buf.len(), self.data.len()));
+
+            buf[..length].copy_from_slice(&self.data[..length]);
+            self.data = &self.data[length..];
+
+            return Ok(length);
+        }
+    }
+
+    #[test]
+    fn decodes_from_unbuffered_reader() {
+        let mut data = vec![0x0D, 0xF0, 0x80, 0x09, 0xF1, 0xEF];
+        data.extend(vec![0x7A; 0xEF]);
+        data.extend(vec![0x08, 0x02, 0x01, 0x02, 0x00]);
+
+        let dictionary = vec!["🍪"];
+        let reader = TrickleReader { data: &data };
+
+        let decoder = ReadDecoder::new(reader, &dictionary[..]);
+        let events: Vec<EventBuf> = decoder.collect::<DecoderResult<_>>().unwrap();
+
+        assert_eq!(events, vec![
+            EventBuf::StartOpenStruct(Size::Streaming),
+            EventBuf::String("🍪".to_string()),
+            EventBuf::String("z".repeat(0xEF)),
+            EventBuf::Binary(vec![0x01, 0x02]),
+            EventBuf::End
+        ]);
+    }
+
+    #[test]
+    fn reports_truncated_payload() {
+        let data = vec![0x08, 0x04, 0x01, 0x02];
+        let dictionary: Vec<&'static str> = vec![];
+
+        let mut decoder = ReadDecoder::new(&data[..], &dictionary[..]);
+
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::UnexpectedEOF));
+    }
+
+    #[test]
+    fn reports_invalid_utf8() {
+        let data = vec![0x62, 0xC3, 0x28];
+        let dictionary: Vec<&'static str> = vec![];
+
+        let mut decoder = ReadDecoder::new(&data[..], &dictionary[..]);
+
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::InvalidUTF8));
+    }
+
+    #[test]
+    fn rejects_payload_beyond_size_limit() {
+        let data = vec![0x08, 0xF3, 0x00, 0x00, 0x00, 0x40];
+        let dictionary: Vec<&'static str> = vec![];
+
+        let limits = DecoderLimits { max_size: 0x1000, ..DecoderLimits::unlimited() };
+        let mut decoder = ReadDecoder::with_limits(&data[..], &dictionary[..], limits);
+
+        assert_eq!(decoder.read().unwrap_err().code(), Some(&ErrorCode::SizeLimitExceeded));
+    }
+
+    #[test]
+    fn rejects_strict_option() {
+        let dictionary: Vec<&'static str> = vec![];
+        let options = DecoderOptions { strict: true, ..DecoderOptions::default() };
+
+        assert_eq!(ReadDecoder::with_options(&[0x01][..], &dictionary[..], options).err().unwrap().code(), Some(&ErrorCode::Unsupported));
+    }
+}
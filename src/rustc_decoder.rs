@@ -0,0 +1,449 @@
+//! `rustc_serialize::Decoder` support for the sofa `Decoder`.
+
+use rustc_serialize;
+
+use super::Event;
+
+use decoder::Decoder;
+use decoder_error::{DecoderError, DecoderResult, ErrorCode};
+
+impl<'a> Decoder<'a> {
+    fn read_fields<T, F>(&mut self, name: &str, len: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        let result = try!(f(self));
+
+        if !self.at_end() {
+            return Err(self.locate_error(DecoderError::ExtraField(name.to_string(), len)));
+        }
+
+        try!(self.expect_end());
+
+        return Ok(result);
+    }
+
+    fn read_field<T, F>(&mut self, name: &str, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        if self.at_end() {
+            return Err(self.locate_error(DecoderError::MissingField(name.to_string())));
+        }
+
+        return f(self);
+    }
+
+    fn read_variant_field<T, F>(&mut self, name: &str, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        if let Some(variant) = self.unit_variant {
+            let error = DecoderError::TypeMismatch("variant with fields".to_string(), format!("variant {}", variant));
+
+            return Err(self.locate_error(error));
+        }
+
+        return self.read_field(name, f);
+    }
+
+    fn read_variant<T, F>(&mut self, names: &[&str], mut f: F) -> DecoderResult<T>
+        where F: FnMut(&mut Decoder<'a>, usize) -> DecoderResult<T>
+    {
+        let expected = format!("one of {}", names.join(", "));
+
+        let (name, fields) = match try!(self.peek()) {
+            Some(Event::StartStruct(_)) => {
+                try!(self.read());
+
+                (try!(self.expect_str()), true)
+            }
+            _ => (try!(self.expect_str()), false)
+        };
+
+        let index = match names.iter().position(|n| *n == name) {
+            Some(index) => index,
+            None => {
+                let error = DecoderError::TypeMismatch(expected, format!("variant {}", name));
+
+                return Err(self.locate_error(error));
+            }
+        };
+
+        if !fields {
+            self.unit_variant = Some(name);
+
+            let result = f(self, index);
+
+            self.unit_variant = None;
+
+            return result;
+        }
+
+        let before = try!(self.remaining());
+        let result = try!(f(self, index));
+        let after = try!(self.remaining());
+
+        if after > 0 {
+            return Err(self.locate_error(DecoderError::ExtraField(name.to_string(), (before - after) as usize)));
+        }
+
+        try!(self.expect_end());
+
+        return Ok(result);
+    }
+}
+
+impl<'a> rustc_serialize::Decoder for Decoder<'a> {
+    type Error = DecoderError;
+
+    fn read_nil(&mut self) -> DecoderResult<()> {
+        return self.expect_nil();
+    }
+
+    fn read_usize(&mut self) -> DecoderResult<usize> {
+        let v = try!(self.expect_u64());
+
+        if v > usize::MAX as u64 {
+            return Err(self.locate_error(DecoderError::StreamError(ErrorCode::Overflow)));
+        }
+
+        return Ok(v as usize);
+    }
+
+    fn read_u64(&mut self) -> DecoderResult<u64> {
+        return self.expect_u64();
+    }
+
+    fn read_u32(&mut self) -> DecoderResult<u32> {
+        return self.expect_u32();
+    }
+
+    fn read_u16(&mut self) -> DecoderResult<u16> {
+        return self.expect_u16();
+    }
+
+    fn read_u8(&mut self) -> DecoderResult<u8> {
+        return self.expect_u8();
+    }
+
+    fn read_isize(&mut self) -> DecoderResult<isize> {
+        let v = try!(self.expect_i64());
+
+        if v < isize::MIN as i64 || v > isize::MAX as i64 {
+            return Err(self.locate_error(DecoderError::StreamError(ErrorCode::Overflow)));
+        }
+
+        return Ok(v as isize);
+    }
+
+    fn read_i64(&mut self) -> DecoderResult<i64> {
+        return self.expect_i64();
+    }
+
+    fn read_i32(&mut self) -> DecoderResult<i32> {
+        return self.expect_i32();
+    }
+
+    fn read_i16(&mut self) -> DecoderResult<i16> {
+        return self.expect_i16();
+    }
+
+    fn read_i8(&mut self) -> DecoderResult<i8> {
+        return self.expect_i8();
+    }
+
+    fn read_bool(&mut self) -> DecoderResult<bool> {
+        return self.expect_bool();
+    }
+
+    fn read_f64(&mut self) -> DecoderResult<f64> {
+        return self.expect_f64();
+    }
+
+    fn read_f32(&mut self) -> DecoderResult<f32> {
+        return self.expect_f32();
+    }
+
+    fn read_char(&mut self) -> DecoderResult<char> {
+        let s = try!(self.expect_str());
+        let mut chars = s.chars();
+
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => {
+                let error = DecoderError::TypeMismatch("char".to_string(), format!("string {:?}", s));
+
+                Err(self.locate_error(error))
+            }
+        };
+    }
+
+    fn read_str(&mut self) -> DecoderResult<String> {
+        return self.expect_str().map(|s| s.to_string());
+    }
+
+    fn read_enum<T, F>(&mut self, _name: &str, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return f(self);
+    }
+
+    fn read_enum_variant<T, F>(&mut self, names: &[&str], f: F) -> DecoderResult<T>
+        where F: FnMut(&mut Decoder<'a>, usize) -> DecoderResult<T>
+    {
+        return self.read_variant(names, f);
+    }
+
+    fn read_enum_variant_arg<T, F>(&mut self, a_idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return self.read_variant_field(&a_idx.to_string(), f);
+    }
+
+    fn read_enum_struct_variant<T, F>(&mut self, names: &[&str], f: F) -> DecoderResult<T>
+        where F: FnMut(&mut Decoder<'a>, usize) -> DecoderResult<T>
+    {
+        return self.read_variant(names, f);
+    }
+
+    fn read_enum_struct_variant_field<T, F>(&mut self, f_name: &str, _f_idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return self.read_variant_field(f_name, f);
+    }
+
+    fn read_struct<T, F>(&mut self, s_name: &str, len: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        try!(self.expect_struct(s_name));
+
+        return self.read_fields(s_name, len, f);
+    }
+
+    fn read_struct_field<T, F>(&mut self, f_name: &str, _f_idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return self.read_field(f_name, f);
+    }
+
+    fn read_tuple<T, F>(&mut self, len: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        try!(self.expect_array());
+
+        let found = try!(self.remaining()) as usize;
+
+        if found != len {
+            let error = DecoderError::TypeMismatch(format!("tuple of {}", len), format!("array of {}", found));
+
+            return Err(self.locate_error(error));
+        }
+
+        let result = try!(f(self));
+
+        try!(self.expect_end());
+
+        return Ok(result);
+    }
+
+    fn read_tuple_arg<T, F>(&mut self, _a_idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return f(self);
+    }
+
+    fn read_tuple_struct<T, F>(&mut self, s_name: &str, len: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return self.read_struct(s_name, len, f);
+    }
+
+    fn read_tuple_struct_arg<T, F>(&mut self, a_idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return self.read_field(&a_idx.to_string(), f);
+    }
+
+    fn read_option<T, F>(&mut self, mut f: F) -> DecoderResult<T>
+        where F: FnMut(&mut Decoder<'a>, bool) -> DecoderResult<T>
+    {
+        if self.at_nil() {
+            try!(self.expect_nil());
+
+            return f(self, false);
+        }
+
+        return f(self, true);
+    }
+
+    fn read_seq<T, F>(&mut self, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>, usize) -> DecoderResult<T>
+    {
+        try!(self.expect_array());
+
+        let len = try!(self.remaining()) as usize;
+        let result = try!(f(self, len));
+
+        try!(self.expect_end());
+
+        return Ok(result);
+    }
+
+    fn read_seq_elt<T, F>(&mut self, _idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return f(self);
+    }
+
+    fn read_map<T, F>(&mut self, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>, usize) -> DecoderResult<T>
+    {
+        try!(self.expect_map());
+
+        let len = try!(self.remaining()) as usize / 2;
+        let result = try!(f(self, len));
+
+        try!(self.expect_end());
+
+        return Ok(result);
+    }
+
+    fn read_map_elt_key<T, F>(&mut self, _idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return f(self);
+    }
+
+    fn read_map_elt_val<T, F>(&mut self, _idx: usize, f: F) -> DecoderResult<T>
+        where F: FnOnce(&mut Decoder<'a>) -> DecoderResult<T>
+    {
+        return f(self);
+    }
+
+    fn error(&mut self, err: &str) -> DecoderError {
+        return self.locate_error(DecoderError::ApplicationError(err.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io;
+
+    use rustc_serialize;
+    use rustc_serialize::Decodable;
+
+    use decoder::Decoder;
+    use decoder_error::{DecoderError, DecoderResult, Location};
+    use decoder_options::DecoderOptions;
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: u32,
+        y: Option<i16>,
+        tags: Vec<String>
+    }
+
+    impl Decodable for Point {
+        fn decode<D: rustc_serialize::Decoder>(d: &mut D) -> Result<Point, D::Error> {
+            return d.read_struct("Point", 3, |d| {
+                return Ok(Point {
+                    x: try!(d.read_struct_field("x", 0, Decodable::decode)),
+                    y: try!(d.read_struct_field("y", 1, Decodable::decode)),
+                    tags: try!(d.read_struct_field("tags", 2, Decodable::decode))
+                });
+            });
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(u8)
+    }
+
+    impl Decodable for Shape {
+        fn decode<D: rustc_serialize::Decoder>(d: &mut D) -> Result<Shape, D::Error> {
+            return d.read_enum("Shape", |d| {
+                return d.read_enum_variant(&["Empty", "Circle"], |d, i| match i {
+                    0 => Ok(Shape::Empty),
+                    _ => Ok(Shape::Circle(try!(d.read_enum_variant_arg(0, Decodable::decode))))
+                });
+            });
+        }
+    }
+
+    fn decode<T: Decodable>(data: &[u8], dictionary: &[&str]) -> DecoderResult<T> {
+        let mut cursor = io::Cursor::new(data);
+        let mut decoder = Decoder::new(&mut cursor, dictionary);
+
+        return Decodable::decode(&mut decoder);
+    }
+
+    #[test]
+    fn decodes_structs() {
+        let sized = [0x0B, 0x03, 0x80, 0x10, 0x01, 0x01, 0x21, 0x61, 0x61];
+        let streaming = [0x0B, 0xF0, 0x80, 0x10, 0x01, 0x15, 0xFE, 0xFF, 0x0A, 0xF0, 0x61, 0x61, 0x00, 0x00];
+        let dictionary = ["Point"];
+
+        assert_eq!(decode(&sized, &dictionary), Ok(Point { x: 1, y: None, tags: vec!["a".to_string()] }));
+        assert_eq!(decode(&streaming, &dictionary), Ok(Point { x: 1, y: Some(-2), tags: vec!["a".to_string()] }));
+    }
+
+    #[test]
+    fn decodes_enums_and_maps() {
+        let data = [0x22, 0x65, 0x45, 0x6D, 0x70, 0x74, 0x79, 0x0B, 0x01, 0x66, 0x43, 0x69, 0x72, 0x63, 0x6C, 0x65, 0x10, 0x02];
+
+        assert_eq!(decode(&data, &[]), Ok((Shape::Empty, Shape::Circle(2))));
+
+        let data = [0x32, 0x80, 0x12, 0x07, 0x00, 0x00, 0x00, 0x61, 0x62, 0x01];
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), Some(7u32));
+        map.insert("b".to_string(), None);
+
+        assert_eq!(decode(&data, &["a"]), Ok(map));
+    }
+
+    #[test]
+    fn decodes_narrow_fields_when_widening() {
+        let data = [0x23, 0x0B, 0x01, 0x66, 0x43, 0x69, 0x72, 0x63, 0x6C, 0x65, 0x10, 0x02, 0x1A, 0x00, 0x00, 0xC0, 0x3F, 0x10, 0x05];
+        let dictionary: [&str; 0] = [];
+        let mut cursor = io::Cursor::new(&data[..]);
+
+        let options = DecoderOptions { widen: true, ..DecoderOptions::default() };
+        let mut decoder = Decoder::with_options(&mut cursor, &dictionary, options);
+
+        assert_eq!(Decodable::decode(&mut decoder), Ok((Shape::Circle(2), 1.5f32, 5i64)));
+    }
+
+    #[test]
+    fn reports_missing_and_extra_fields() {
+        let missing = [0x0B, 0x02, 0x80, 0x10, 0x01, 0x01];
+        let extra = [0x0B, 0x04, 0x80, 0x10, 0x01, 0x01, 0x20, 0x03];
+        let dictionary = ["Point"];
+
+        let location = Location { offset: 6, path: "[2]".to_string() };
+        let error = DecoderError::MissingField("tags".to_string());
+
+        assert_eq!(decode::<Point>(&missing, &dictionary), Err(DecoderError::LocatedError(location, Box::new(error))));
+
+        let location = Location { offset: 7, path: "[3]".to_string() };
+        let error = DecoderError::ExtraField("Point".to_string(), 3);
+
+        assert_eq!(decode::<Point>(&extra, &dictionary), Err(DecoderError::LocatedError(location, Box::new(error))));
+    }
+
+    #[test]
+    fn reports_unknown_variants() {
+        let data = [0x66, 0x53, 0x71, 0x75, 0x61, 0x72, 0x65];
+
+        assert_eq!(decode::<Shape>(&data, &[]).unwrap_err().to_string(),
+                   "invalid type: expected one of Empty, Circle, found variant Square at byte 7");
+    }
+
+    #[test]
+    fn rejects_arguments_of_unit_variants() {
+        let data = [0x22, 0x66, 0x43, 0x69, 0x72, 0x63, 0x6C, 0x65, 0x10, 0x02];
+
+        assert_eq!(decode::<(Shape, u8)>(&data, &[]).unwrap_err().to_string(),
+                   "invalid type: expected variant with fields, found variant Circle at byte 8 ([1])");
+    }
+}
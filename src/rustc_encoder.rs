@@ -0,0 +1,314 @@
+//! `rustc_serialize::Encoder` support for the sofa `Encoder`.
+
+use rustc_serialize;
+
+use super::{Event, Size};
+
+use encoder::Encoder;
+use encoder_error::{EncoderError, EncoderResult};
+
+impl<'a> Encoder<'a> {
+    fn emit_container<F>(&mut self, start: Event, name: Option<&str>, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        try!(self.write(&start));
+
+        if let Some(name) = name {
+            try!(self.write(&Event::String(name)));
+        }
+
+        try!(f(self));
+
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a> rustc_serialize::Encoder for Encoder<'a> {
+    type Error = EncoderError;
+
+    fn emit_nil(&mut self) -> EncoderResult<()> {
+        return self.write(&Event::Nil);
+    }
+
+    fn emit_usize(&mut self, v: usize) -> EncoderResult<()> {
+        return self.write(&Event::U64(v as u64));
+    }
+
+    fn emit_u64(&mut self, v: u64) -> EncoderResult<()> {
+        return self.write(&Event::U64(v));
+    }
+
+    fn emit_u32(&mut self, v: u32) -> EncoderResult<()> {
+        return self.write(&Event::U32(v));
+    }
+
+    fn emit_u16(&mut self, v: u16) -> EncoderResult<()> {
+        return self.write(&Event::U16(v));
+    }
+
+    fn emit_u8(&mut self, v: u8) -> EncoderResult<()> {
+        return self.write(&Event::U8(v));
+    }
+
+    fn emit_isize(&mut self, v: isize) -> EncoderResult<()> {
+        return self.write(&Event::I64(v as i64));
+    }
+
+    fn emit_i64(&mut self, v: i64) -> EncoderResult<()> {
+        return self.write(&Event::I64(v));
+    }
+
+    fn emit_i32(&mut self, v: i32) -> EncoderResult<()> {
+        return self.write(&Event::I32(v));
+    }
+
+    fn emit_i16(&mut self, v: i16) -> EncoderResult<()> {
+        return self.write(&Event::I16(v));
+    }
+
+    fn emit_i8(&mut self, v: i8) -> EncoderResult<()> {
+        return self.write(&Event::I8(v));
+    }
+
+    fn emit_bool(&mut self, v: bool) -> EncoderResult<()> {
+        return self.write(&Event::Boolean(v));
+    }
+
+    fn emit_f64(&mut self, v: f64) -> EncoderResult<()> {
+        return self.write(&Event::F64(v));
+    }
+
+    fn emit_f32(&mut self, v: f32) -> EncoderResult<()> {
+        return self.write(&Event::F32(v));
+    }
+
+    fn emit_char(&mut self, v: char) -> EncoderResult<()> {
+        let mut buffer = [0; 4];
+
+        return self.write(&Event::String(v.encode_utf8(&mut buffer)));
+    }
+
+    fn emit_str(&mut self, v: &str) -> EncoderResult<()> {
+        return self.write(&Event::String(v));
+    }
+
+    fn emit_enum<F>(&mut self, _name: &str, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    // A variant without arguments is written as a bare string with its name, any other variant as
+    // a `StartStruct` named after the variant holding the arguments.
+    fn emit_enum_variant<F>(&mut self, v_name: &str, _v_id: usize, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        if len == 0 {
+            return self.write(&Event::String(v_name));
+        }
+
+        return self.emit_container(Event::StartStruct(Size::U64(len as u64)), Some(v_name), f);
+    }
+
+    fn emit_enum_variant_arg<F>(&mut self, _a_idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_enum_struct_variant<F>(&mut self, v_name: &str, v_id: usize, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return self.emit_enum_variant(v_name, v_id, len, f);
+    }
+
+    fn emit_enum_struct_variant_field<F>(&mut self, _f_name: &str, _f_idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_struct<F>(&mut self, name: &str, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return self.emit_container(Event::StartStruct(Size::U64(len as u64)), Some(name), f);
+    }
+
+    fn emit_struct_field<F>(&mut self, _f_name: &str, _f_idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_tuple<F>(&mut self, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return self.emit_container(Event::StartArray(Size::U64(len as u64)), None, f);
+    }
+
+    fn emit_tuple_arg<F>(&mut self, _idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_tuple_struct<F>(&mut self, name: &str, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return self.emit_container(Event::StartStruct(Size::U64(len as u64)), Some(name), f);
+    }
+
+    fn emit_tuple_struct_arg<F>(&mut self, _f_idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_option<F>(&mut self, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    // `Some(v)` is written as `v`, so `None`, `Some(None)` and `Some(())` all become nil and
+    // `Option<()>` or nested options do not round-trip.
+    fn emit_option_none(&mut self) -> EncoderResult<()> {
+        return self.write(&Event::Nil);
+    }
+
+    fn emit_option_some<F>(&mut self, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_seq<F>(&mut self, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return self.emit_container(Event::StartArray(Size::U64(len as u64)), None, f);
+    }
+
+    fn emit_seq_elt<F>(&mut self, _idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_map<F>(&mut self, len: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return self.emit_container(Event::StartMap(Size::U64(len as u64)), None, f);
+    }
+
+    fn emit_map_elt_key<F>(&mut self, _idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+
+    fn emit_map_elt_val<F>(&mut self, _idx: usize, f: F) -> EncoderResult<()>
+        where F: FnOnce(&mut Encoder<'a>) -> EncoderResult<()>
+    {
+        return f(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rustc_serialize;
+    use rustc_serialize::Encodable;
+
+    use encoder::Encoder;
+
+    struct Point {
+        x: u8,
+        y: Option<i16>,
+        tags: Vec<String>
+    }
+
+    impl Encodable for Point {
+        fn encode<S: rustc_serialize::Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+            return s.emit_struct("Point", 3, |s| {
+                try!(s.emit_struct_field("x", 0, |s| self.x.encode(s)));
+                try!(s.emit_struct_field("y", 1, |s| self.y.encode(s)));
+                return s.emit_struct_field("tags", 2, |s| self.tags.encode(s));
+            });
+        }
+    }
+
+    enum Shape {
+        Empty,
+        Circle(u8)
+    }
+
+    impl Encodable for Shape {
+        fn encode<S: rustc_serialize::Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+            return s.emit_enum("Shape", |s| match *self {
+                Shape::Empty => s.emit_enum_variant("Empty", 0, 0, |_| Ok(())),
+                Shape::Circle(ref r) => s.emit_enum_variant("Circle", 1, 1, |s| {
+                    s.emit_enum_variant_arg(0, |s| r.encode(s))
+                })
+            });
+        }
+    }
+
+    #[test]
+    fn encodes_structs() {
+        let point = Point { x: 1, y: None, tags: vec!["a".to_string()] };
+        let dictionary = ["Point"];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+            point.encode(&mut encoder).unwrap();
+        }
+
+        assert_eq!(buffer, vec![0x0B, 0x03, 0x80, 0x10, 0x01, 0x01, 0x21, 0x61, 0x61]);
+    }
+
+    #[test]
+    fn encodes_enums() {
+        let shapes = (Shape::Empty, Shape::Circle(2));
+        let dictionary: [&str; 0] = [];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+            shapes.encode(&mut encoder).unwrap();
+        }
+
+        assert_eq!(buffer, vec![0x22, 0x65, 0x45, 0x6D, 0x70, 0x74, 0x79,
+                                0x0B, 0x01, 0x66, 0x43, 0x69, 0x72, 0x63, 0x6C, 0x65, 0x10, 0x02]);
+    }
+
+    #[test]
+    fn encodes_maps() {
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), Some(7u32));
+        let dictionary = ["a"];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+            map.encode(&mut encoder).unwrap();
+        }
+
+        assert_eq!(buffer, vec![0x31, 0x80, 0x12, 0x07, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn encodes_nested_options_as_nil() {
+        let options = (None::<Option<u8>>, Some(None::<u8>), Some(()));
+        let dictionary: [&str; 0] = [];
+        let mut buffer = Vec::new();
+
+        {
+            let mut encoder = Encoder::new(&mut buffer, &dictionary[..]);
+            options.encode(&mut encoder).unwrap();
+        }
+
+        assert_eq!(buffer, vec![0x23, 0x01, 0x01, 0x01]);
+    }
+}
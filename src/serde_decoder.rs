@@ -0,0 +1,524 @@
+//! `serde::Deserializer` support for the sofa `Decoder`, enabled by the `serde` feature.
+
+use serde::de;
+use serde::de::value::BorrowedStrDeserializer;
+
+use super::Event;
+
+use decoder::Decoder;
+use decoder_error::{DecoderError, DecoderResult};
+use decoder_options::DecoderOptions;
+use fixnum;
+
+impl de::Error for DecoderError {
+    fn custom<T: ::std::fmt::Display>(msg: T) -> DecoderError {
+        return DecoderError::ApplicationError(msg.to_string());
+    }
+}
+
+pub fn from_decoder<'de, T: de::Deserialize<'de>>(decoder: &mut Decoder<'de>) -> DecoderResult<T> {
+    return T::deserialize(decoder);
+}
+
+pub fn from_slice<'de, T: de::Deserialize<'de>>(input: &'de [u8], dictionary: &'de [&'de str]) -> DecoderResult<T> {
+    let mut decoder = Decoder::with_slice(input, dictionary, DecoderOptions::default());
+
+    return from_decoder(&mut decoder);
+}
+
+impl<'de> Decoder<'de> {
+    fn located<T>(&self, result: DecoderResult<T>) -> DecoderResult<T> {
+        return result.map_err(|e| match e {
+            DecoderError::LocatedError(..) => e,
+            _ => self.locate_error(e)
+        });
+    }
+
+    fn end_fields(&mut self, name: &str, len: usize) -> DecoderResult<()> {
+        if !self.at_end() {
+            return Err(self.locate_error(DecoderError::ExtraField(name.to_string(), len)));
+        }
+
+        return self.expect_end();
+    }
+
+    fn visit_elements<V: de::Visitor<'de>>(&mut self, visitor: V) -> DecoderResult<V::Value> {
+        let result = visitor.visit_seq(Elements { decoder: self });
+        let value = try!(self.located(result));
+
+        try!(self.expect_end());
+
+        return Ok(value);
+    }
+
+    fn visit_entries<V: de::Visitor<'de>>(&mut self, visitor: V) -> DecoderResult<V::Value> {
+        let result = visitor.visit_map(Entries { decoder: self });
+        let value = try!(self.located(result));
+
+        try!(self.expect_end());
+
+        return Ok(value);
+    }
+
+    fn visit_fields<V: de::Visitor<'de>>(&mut self, name: &str, len: usize, visitor: V) -> DecoderResult<V::Value> {
+        let result = visitor.visit_seq(Elements { decoder: self });
+        let value = try!(self.located(result));
+
+        try!(self.end_fields(name, len));
+
+        return Ok(value);
+    }
+}
+
+macro_rules! deserialize_with {
+    ($name:ident, $expect:ident, $visit:ident) => {
+        fn $name<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+            let v = try!(self.$expect());
+            let result = visitor.$visit(v);
+
+            return self.located(result);
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = DecoderError;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        let event = try!(self.expect("value", |event| match *event {
+            Event::End => None,
+            ref event => Some(event.clone())
+        }));
+
+        let result = match event {
+            Event::Nil => visitor.visit_unit(),
+            Event::Boolean(v) => visitor.visit_bool(v),
+            Event::U8(v) => visitor.visit_u8(v),
+            Event::U16(v) => visitor.visit_u16(v),
+            Event::U32(v) => visitor.visit_u32(v),
+            Event::U64(v) => visitor.visit_u64(v),
+            Event::I8(v) => visitor.visit_i8(v),
+            Event::I16(v) => visitor.visit_i16(v),
+            Event::I32(v) => visitor.visit_i32(v),
+            Event::I64(v) => visitor.visit_i64(v),
+            Event::Fixnum(bytes) => match fixnum::to_i128(bytes) {
+                Ok(v) => visitor.visit_i128(v),
+                Err(_) => visitor.visit_u128(try!(self.located(fixnum::to_u128(bytes))))
+            },
+            Event::F32(v) => visitor.visit_f32(v),
+            Event::F64(v) => visitor.visit_f64(v),
+            Event::Binary(v) => visitor.visit_borrowed_bytes(v),
+            Event::String(v) => visitor.visit_borrowed_str(v),
+            Event::StartArray(_) => return self.visit_elements(visitor),
+            Event::StartStruct(_) => {
+                try!(self.expect_str());
+
+                return self.visit_elements(visitor);
+            }
+            Event::StartMap(_) => return self.visit_entries(visitor),
+            Event::StartOpenStruct(_) => {
+                try!(self.expect_str());
+
+                return self.visit_entries(visitor);
+            }
+            Event::End => unreachable!()
+        };
+
+        return self.located(result);
+    }
+
+    deserialize_with!(deserialize_bool, expect_bool, visit_bool);
+    deserialize_with!(deserialize_u8, expect_u8, visit_u8);
+    deserialize_with!(deserialize_u16, expect_u16, visit_u16);
+    deserialize_with!(deserialize_u32, expect_u32, visit_u32);
+    deserialize_with!(deserialize_u64, expect_u64, visit_u64);
+    deserialize_with!(deserialize_i8, expect_i8, visit_i8);
+    deserialize_with!(deserialize_i16, expect_i16, visit_i16);
+    deserialize_with!(deserialize_i32, expect_i32, visit_i32);
+    deserialize_with!(deserialize_i64, expect_i64, visit_i64);
+    deserialize_with!(deserialize_f32, expect_f32, visit_f32);
+    deserialize_with!(deserialize_f64, expect_f64, visit_f64);
+    deserialize_with!(deserialize_char, expect_str, visit_borrowed_str);
+    deserialize_with!(deserialize_str, expect_str, visit_borrowed_str);
+    deserialize_with!(deserialize_string, expect_str, visit_borrowed_str);
+    deserialize_with!(deserialize_identifier, expect_str, visit_borrowed_str);
+    deserialize_with!(deserialize_bytes, expect_binary, visit_borrowed_bytes);
+    deserialize_with!(deserialize_byte_buf, expect_binary, visit_borrowed_bytes);
+
+    fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        let v = try!(self.expect("i128", |event| match *event {
+            Event::U8(v) => Some(Ok(i128::from(v))),
+            Event::U16(v) => Some(Ok(i128::from(v))),
+            Event::U32(v) => Some(Ok(i128::from(v))),
+            Event::I8(v) => Some(Ok(i128::from(v))),
+            Event::I16(v) => Some(Ok(i128::from(v))),
+            Event::I32(v) => Some(Ok(i128::from(v))),
+            Event::I64(v) => Some(Ok(i128::from(v))),
+            Event::Fixnum(bytes) => Some(fixnum::to_i128(bytes)),
+            _ => None
+        }));
+
+        let result = visitor.visit_i128(try!(self.located(v)));
+
+        return self.located(result);
+    }
+
+    fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        let v = try!(self.expect("u128", |event| match *event {
+            Event::U8(v) => Some(Ok(u128::from(v))),
+            Event::U16(v) => Some(Ok(u128::from(v))),
+            Event::U32(v) => Some(Ok(u128::from(v))),
+            Event::U64(v) => Some(Ok(u128::from(v))),
+            Event::Fixnum(bytes) => Some(fixnum::to_u128(bytes)),
+            _ => None
+        }));
+
+        let result = visitor.visit_u128(try!(self.located(v)));
+
+        return self.located(result);
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        if self.at_nil() {
+            try!(self.expect_nil());
+
+            let result = visitor.visit_none();
+
+            return self.located(result);
+        }
+
+        let result = visitor.visit_some(&mut *self);
+
+        return self.located(result);
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_nil());
+
+        let result = visitor.visit_unit();
+
+        return self.located(result);
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(self, name: &'static str, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_struct(name));
+        try!(self.end_fields(name, 0));
+
+        let result = visitor.visit_unit();
+
+        return self.located(result);
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(self, name: &'static str, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_struct(name));
+
+        let result = visitor.visit_newtype_struct(&mut *self);
+        let value = try!(self.located(result));
+
+        try!(self.end_fields(name, 1));
+
+        return Ok(value);
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_array());
+
+        return self.visit_elements(visitor);
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> DecoderResult<V::Value> {
+        return self.deserialize_seq(visitor);
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_struct(name));
+
+        return self.visit_fields(name, len, visitor);
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_map());
+
+        return self.visit_entries(visitor);
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> DecoderResult<V::Value> {
+        let event = try!(self.expect_named("struct", name, |event| match *event {
+            Event::StartStruct(_) | Event::StartMap(_) | Event::StartOpenStruct(_) => Some(event.clone()),
+            _ => None
+        }));
+
+        return match event {
+            Event::StartStruct(_) => self.visit_fields(name, fields.len(), visitor),
+            _ => self.visit_entries(visitor)
+        };
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> DecoderResult<V::Value> {
+        let result = visitor.visit_enum(Variant { decoder: &mut *self, name: "", fields: false });
+
+        return self.located(result);
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.skip_value());
+
+        let result = visitor.visit_unit();
+
+        return self.located(result);
+    }
+
+    fn is_human_readable(&self) -> bool {
+        return false;
+    }
+}
+
+struct Elements<'b, 'de: 'b> {
+    decoder: &'b mut Decoder<'de>
+}
+
+impl<'b, 'de> de::SeqAccess<'de> for Elements<'b, 'de> {
+    type Error = DecoderError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> DecoderResult<Option<T::Value>> {
+        if self.decoder.at_end() {
+            return Ok(None);
+        }
+
+        return seed.deserialize(&mut *self.decoder).map(Some);
+    }
+}
+
+struct Entries<'b, 'de: 'b> {
+    decoder: &'b mut Decoder<'de>
+}
+
+impl<'b, 'de> de::MapAccess<'de> for Entries<'b, 'de> {
+    type Error = DecoderError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> DecoderResult<Option<K::Value>> {
+        if self.decoder.at_end() {
+            return Ok(None);
+        }
+
+        return seed.deserialize(&mut *self.decoder).map(Some);
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> DecoderResult<V::Value> {
+        return seed.deserialize(&mut *self.decoder);
+    }
+}
+
+struct Variant<'b, 'de: 'b> {
+    decoder: &'b mut Decoder<'de>,
+    name: &'de str,
+    fields: bool
+}
+
+impl<'b, 'de> Variant<'b, 'de> {
+    fn expect_fields(&self, expected: &str) -> DecoderResult<()> {
+        if !self.fields {
+            let error = DecoderError::TypeMismatch(expected.to_string(), format!("unit variant {}", self.name));
+
+            return Err(self.decoder.locate_error(error));
+        }
+
+        return Ok(());
+    }
+}
+
+impl<'b, 'de> de::EnumAccess<'de> for Variant<'b, 'de> {
+    type Error = DecoderError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(mut self, seed: V) -> DecoderResult<(V::Value, Self)> {
+        let name = try!(self.decoder.expect("variant", |event| match *event {
+            Event::StartStruct(_) => Some(None),
+            Event::String(v) => Some(Some(v)),
+            _ => None
+        }));
+
+        self.name = match name {
+            Some(name) => name,
+            None => {
+                self.fields = true;
+
+                try!(self.decoder.expect_str())
+            }
+        };
+
+        let value = try!(seed.deserialize(BorrowedStrDeserializer::<DecoderError>::new(self.name)));
+
+        return Ok((value, self));
+    }
+}
+
+impl<'b, 'de> de::VariantAccess<'de> for Variant<'b, 'de> {
+    type Error = DecoderError;
+
+    fn unit_variant(self) -> DecoderResult<()> {
+        if self.fields {
+            return self.decoder.end_fields(self.name, 0);
+        }
+
+        return Ok(());
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> DecoderResult<T::Value> {
+        try!(self.expect_fields("newtype variant"));
+
+        let value = try!(seed.deserialize(&mut *self.decoder));
+
+        try!(self.decoder.end_fields(self.name, 1));
+
+        return Ok(value);
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_fields("tuple variant"));
+
+        return self.decoder.visit_fields(self.name, len, visitor);
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> DecoderResult<V::Value> {
+        try!(self.expect_fields("struct variant"));
+
+        return self.decoder.visit_fields(self.name, fields.len(), visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io;
+
+    use decoder::Decoder;
+    use decoder_error::{DecoderError, Location};
+    use serde_encoder::to_vec;
+
+    use super::{from_decoder, from_slice};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message<'a> {
+        id: u32,
+        name: &'a str,
+        #[serde(with = "bytes")]
+        data: &'a [u8],
+        tags: Vec<String>,
+        shape: Option<Shape>
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(u8),
+        Line { length: u32 }
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Point {
+        x: u8,
+        y: u8
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Any {
+        Integer(u64),
+        Text(String),
+        List(Vec<Any>)
+    }
+
+    mod bytes {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(v: &&[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            return serializer.serialize_bytes(v);
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<&'de [u8], D::Error> {
+            return Deserialize::deserialize(deserializer);
+        }
+    }
+
+    #[test]
+    fn borrows_from_input() {
+        let dictionary = ["Message", "Line"];
+        let shapes = [None, Some(Shape::Empty), Some(Shape::Circle(3)), Some(Shape::Line { length: 7 })];
+
+        for shape in shapes.iter() {
+            let message = Message {
+                id: 7,
+                name: "sofa",
+                data: &[1, 2, 3],
+                tags: vec!["a".to_string()],
+                shape: shape.clone()
+            };
+
+            let data = to_vec(&message, &dictionary).unwrap();
+            let mut cursor = io::Cursor::new(&data[..]);
+            let mut decoder = Decoder::new(&mut cursor, &dictionary);
+
+            let decoded: Message = from_decoder(&mut decoder).unwrap();
+
+            assert_eq!(decoded, message);
+            assert_eq!(decoded.name.as_ptr(), data[9..].as_ptr());
+        }
+    }
+
+    #[test]
+    fn borrows_from_slice() {
+        let dictionary = ["Message"];
+        let message = Message { id: 1, name: "sofa", data: &[4, 5], tags: vec![], shape: None };
+
+        let data = to_vec(&message, &dictionary).unwrap();
+        let decoded: Message = from_slice(&data, &dictionary).unwrap();
+
+        assert_eq!(decoded, message);
+        assert_eq!(decoded.name.as_ptr(), data[9..].as_ptr());
+    }
+
+    #[test]
+    fn decodes_open_structs_by_key() {
+        let data = [0x0D, 0x03, 0x65, 0x50, 0x6F, 0x69, 0x6E, 0x74, 0x61, 0x79, 0x10, 0x02, 0x61, 0x7A, 0x01, 0x61, 0x78, 0x10, 0x01];
+
+        assert_eq!(from_slice(&data, &[]), Ok(Point { x: 1, y: 2 }));
+    }
+
+    #[test]
+    fn reports_struct_mismatches() {
+        let extra = [0x0B, 0x03, 0x65, 0x50, 0x6F, 0x69, 0x6E, 0x74, 0x10, 0x01, 0x10, 0x02, 0x10, 0x03];
+
+        let location = Location { offset: 12, path: "[2]".to_string() };
+        let error = DecoderError::ExtraField("Point".to_string(), 2);
+
+        assert_eq!(from_slice::<Point>(&extra, &[]), Err(DecoderError::LocatedError(location, Box::new(error))));
+
+        let renamed = [0x0B, 0x02, 0x65, 0x50, 0x6F, 0x73, 0x74, 0x74, 0x10, 0x01, 0x10, 0x02];
+
+        assert_eq!(from_slice::<Point>(&renamed, &[]).unwrap_err().to_string(),
+                   "invalid type: expected name \"Point\", found string \"Postt\" at byte 2 (.<name>)");
+    }
+
+    #[test]
+    fn deserializes_any() {
+        let data = [0x23, 0x10, 0x01, 0x61, 0x61, 0x0A, 0xF0, 0x11, 0x02, 0x00, 0x00];
+        let expected = Any::List(vec![Any::Integer(1), Any::Text("a".to_string()), Any::List(vec![Any::Integer(2)])]);
+
+        assert_eq!(from_slice(&data, &[]), Ok(expected));
+
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), Any::Integer(1));
+
+        assert_eq!(from_slice(&[0x31, 0x61, 0x61, 0x10, 0x01], &[]), Ok(map));
+    }
+
+    #[test]
+    fn decodes_wide_integers() {
+        let value = (i128::MIN, u128::MAX, Some(-1i128));
+        let data = to_vec(&value, &[]).unwrap();
+
+        assert_eq!(from_slice(&data, &[]), Ok(value));
+        assert_eq!(from_slice(&[0x23, 0x10, 0x05, 0x14, 0xFF, 0x01], &[]), Ok((5u128, -1i128, None::<u128>)));
+    }
+}
@@ -0,0 +1,358 @@
+//! `serde::Serializer` support for the sofa `Encoder`, enabled by the `serde` feature.
+
+use std::fmt;
+use std::io;
+
+use serde::ser;
+
+use super::{Event, Size};
+
+use encoder::Encoder;
+use encoder_error::{EncoderError, EncoderResult};
+use fixnum;
+
+impl ser::Error for EncoderError {
+    fn custom<T: fmt::Display>(msg: T) -> EncoderError {
+        return EncoderError::ApplicationError(msg.to_string());
+    }
+}
+
+pub fn to_writer<T: ?Sized + ser::Serialize>(writer: &mut io::Write, value: &T, dictionary: &[&str]) -> EncoderResult<()> {
+    let mut encoder = Encoder::new(writer, dictionary);
+
+    try!(value.serialize(&mut encoder));
+
+    return encoder.finish();
+}
+
+pub fn to_vec<T: ?Sized + ser::Serialize>(value: &T, dictionary: &[&str]) -> EncoderResult<Vec<u8>> {
+    let mut buffer = Vec::new();
+
+    try!(to_writer(&mut buffer, value, dictionary));
+
+    return Ok(buffer);
+}
+
+fn size(len: Option<usize>) -> Size {
+    return match len {
+        Some(len) => Size::U64(len as u64),
+        None => Size::Streaming
+    };
+}
+
+impl<'a> Encoder<'a> {
+    fn start_struct(&mut self, name: &str, len: usize) -> EncoderResult<()> {
+        try!(self.write(&Event::StartStruct(Size::U64(len as u64))));
+
+        return self.write(&Event::String(name));
+    }
+}
+
+impl<'a, 'b> ser::Serializer for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> EncoderResult<()> {
+        return self.write(&Event::Boolean(v));
+    }
+
+    fn serialize_i8(self, v: i8) -> EncoderResult<()> {
+        return self.write(&Event::I8(v));
+    }
+
+    fn serialize_i16(self, v: i16) -> EncoderResult<()> {
+        return self.write(&Event::I16(v));
+    }
+
+    fn serialize_i32(self, v: i32) -> EncoderResult<()> {
+        return self.write(&Event::I32(v));
+    }
+
+    fn serialize_i64(self, v: i64) -> EncoderResult<()> {
+        return self.write(&Event::I64(v));
+    }
+
+    fn serialize_i128(self, v: i128) -> EncoderResult<()> {
+        return self.write(&Event::Fixnum(&fixnum::from_i128(v)));
+    }
+
+    fn serialize_u8(self, v: u8) -> EncoderResult<()> {
+        return self.write(&Event::U8(v));
+    }
+
+    fn serialize_u16(self, v: u16) -> EncoderResult<()> {
+        return self.write(&Event::U16(v));
+    }
+
+    fn serialize_u32(self, v: u32) -> EncoderResult<()> {
+        return self.write(&Event::U32(v));
+    }
+
+    fn serialize_u64(self, v: u64) -> EncoderResult<()> {
+        return self.write(&Event::U64(v));
+    }
+
+    fn serialize_u128(self, v: u128) -> EncoderResult<()> {
+        return self.write(&Event::Fixnum(&fixnum::from_u128(v)));
+    }
+
+    fn serialize_f32(self, v: f32) -> EncoderResult<()> {
+        return self.write(&Event::F32(v));
+    }
+
+    fn serialize_f64(self, v: f64) -> EncoderResult<()> {
+        return self.write(&Event::F64(v));
+    }
+
+    fn serialize_char(self, v: char) -> EncoderResult<()> {
+        let mut buffer = [0; 4];
+
+        return self.write(&Event::String(v.encode_utf8(&mut buffer)));
+    }
+
+    fn serialize_str(self, v: &str) -> EncoderResult<()> {
+        return self.write(&Event::String(v));
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> EncoderResult<()> {
+        return self.write(&Event::Binary(v));
+    }
+
+    fn serialize_none(self) -> EncoderResult<()> {
+        return self.write(&Event::Nil);
+    }
+
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> EncoderResult<()> {
+        return value.serialize(self);
+    }
+
+    fn serialize_unit(self) -> EncoderResult<()> {
+        return self.write(&Event::Nil);
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> EncoderResult<()> {
+        try!(self.start_struct(name, 0));
+
+        return self.write(&Event::End);
+    }
+
+    // As in `rustc_encoder`, a unit variant is written as its name and every other variant as a
+    // struct named after the variant holding its fields.
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> EncoderResult<()> {
+        return self.write(&Event::String(variant));
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(self, name: &'static str, value: &T) -> EncoderResult<()> {
+        try!(self.start_struct(name, 1));
+        try!(value.serialize(&mut *self));
+
+        return self.write(&Event::End);
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> EncoderResult<()> {
+        return self.serialize_newtype_struct(variant, value);
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> EncoderResult<Self> {
+        try!(self.write(&Event::StartArray(size(len))));
+
+        return Ok(self);
+    }
+
+    fn serialize_tuple(self, len: usize) -> EncoderResult<Self> {
+        return self.serialize_seq(Some(len));
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> EncoderResult<Self> {
+        try!(self.start_struct(name, len));
+
+        return Ok(self);
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> EncoderResult<Self> {
+        return self.serialize_tuple_struct(variant, len);
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> EncoderResult<Self> {
+        try!(self.write(&Event::StartMap(size(len))));
+
+        return Ok(self);
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> EncoderResult<Self> {
+        return self.serialize_tuple_struct(name, len);
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> EncoderResult<Self> {
+        return self.serialize_tuple_struct(variant, len);
+    }
+}
+
+impl<'a, 'b> ser::SerializeSeq for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a, 'b> ser::SerializeTuple for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a, 'b> ser::SerializeTupleStruct for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a, 'b> ser::SerializeTupleVariant for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a, 'b> ser::SerializeMap for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> EncoderResult<()> {
+        return key.serialize(&mut **self);
+    }
+
+    fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a, 'b> ser::SerializeStruct for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, _key: &'static str, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+impl<'a, 'b> ser::SerializeStructVariant for &'b mut Encoder<'a> {
+    type Ok = ();
+    type Error = EncoderError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, _key: &'static str, value: &T) -> EncoderResult<()> {
+        return value.serialize(&mut **self);
+    }
+
+    fn end(self) -> EncoderResult<()> {
+        return self.write(&Event::End);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Serialize, Serializer};
+
+    use super::to_vec;
+
+    #[derive(Serialize)]
+    struct Point {
+        x: u8,
+        y: Option<i16>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(u8),
+        Line { length: u32 }
+    }
+
+    #[derive(Serialize)]
+    struct Meters(f32);
+
+    struct Evens(u8);
+
+    impl Serialize for Evens {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            return serializer.collect_seq((0..self.0).filter(|x| x % 2 == 0));
+        }
+    }
+
+    #[test]
+    fn serializes_structs() {
+        let point = Point { x: 1, y: Some(-2), tags: vec!["a".to_string()] };
+
+        assert_eq!(to_vec(&point, &["Point"]).unwrap(),
+                   vec![0x0B, 0x03, 0x80, 0x10, 0x01, 0x15, 0xFE, 0xFF, 0x21, 0x61, 0x61]);
+
+        let point = Point { x: 1, y: None, tags: vec![] };
+
+        assert_eq!(to_vec(&point, &["Point"]).unwrap(), vec![0x0B, 0x02, 0x80, 0x10, 0x01, 0x01]);
+        assert_eq!(to_vec(&Meters(1.5), &["Meters"]).unwrap(), vec![0x0B, 0x01, 0x80, 0x1A, 0x00, 0x00, 0xC0, 0x3F]);
+    }
+
+    #[test]
+    fn serializes_enums() {
+        let shapes = (Shape::Empty, Shape::Circle(2), Shape::Line { length: 3 });
+
+        assert_eq!(to_vec(&shapes, &["Circle", "Line"]).unwrap(),
+                   vec![0x23, 0x65, 0x45, 0x6D, 0x70, 0x74, 0x79, 0x0B, 0x01, 0x80, 0x10, 0x02, 0x0B, 0x01, 0x81, 0x12, 0x03, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn serializes_maps_and_sequences() {
+        let mut map = BTreeMap::new();
+        map.insert("a", vec![1u8]);
+
+        assert_eq!(to_vec(&map, &["a"]).unwrap(), vec![0x31, 0x80, 0x21, 0x10, 0x01]);
+
+        assert_eq!(to_vec(&Evens(4), &[]).unwrap(), vec![0x0A, 0xF0, 0x10, 0x00, 0x10, 0x02, 0x00]);
+    }
+}
@@ -0,0 +1,53 @@
+use std::cmp;
+
+use super::Size;
+
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use decoder_limits::DecoderLimits;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackSize { Streaming(usize, usize, usize, usize), Counted(usize), U64(usize) }
+
+impl StackSize {
+    pub fn from_size(size: Size, modulo: usize, required: usize, limits: &DecoderLimits) -> DecoderResult<StackSize> {
+        return match size {
+            Size::Streaming => {
+                let maximum = (cmp::min(limits.max_elements, usize::MAX as u64) as usize).saturating_mul(modulo).saturating_add(required);
+
+                Ok(StackSize::Streaming(0, modulo, required, maximum))
+            }
+            Size::U64(size) => {
+                try!(limits.check_elements(size));
+
+                if size > usize::MAX as u64 {
+                    return Err(DecoderError::StreamError(ErrorCode::InvalidLength));
+                }
+
+                match (size as usize).checked_mul(modulo).and_then(|n| n.checked_add(required)) {
+                    Some(n) => Ok(StackSize::U64(n)),
+                    None => Err(DecoderError::StreamError(ErrorCode::InvalidLength))
+                }
+            }
+        };
+    }
+
+    pub fn remove_one(self) -> DecoderResult<StackSize> {
+        return match self {
+            StackSize::Streaming(n, _, _, maximum) if n >= maximum => {
+                Err(DecoderError::StreamError(ErrorCode::ElementLimitExceeded))
+            }
+            StackSize::Streaming(n, modulo, required, maximum) => Ok(StackSize::Streaming(n + 1, modulo, required, maximum)),
+            StackSize::Counted(0) => Err(DecoderError::StreamError(ErrorCode::InvalidEnd)),
+            StackSize::Counted(remaining) => Ok(StackSize::Counted(remaining - 1)),
+            StackSize::U64(remaining) => Ok(StackSize::U64(remaining - 1))
+        };
+    }
+
+    pub fn accepts_end(self) -> bool {
+        return match self {
+            StackSize::Streaming(n, modulo, required, _) => n >= required && (n - required) % modulo == 0,
+            StackSize::Counted(remaining) => remaining == 0,
+            StackSize::U64(remaining) => remaining == 0
+        };
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+use std::str;
+
+use super::{Event, Size};
+
+use canonical;
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use decoder_limits::DecoderLimits;
+
+pub enum Token {
+    Event(Event<'static>),
+    Binary(u64),
+    Fixnum(u64),
+    String(u64),
+    Dictionary(u64)
+}
+
+pub fn read_token<R: io::Read + ?Sized>(reader: &mut R, strict: bool) -> DecoderResult<Token> {
+    let result = match try!(read_u8(reader)) {
+        0x00 => Token::Event(Event::End),
+        0x01 => Token::Event(Event::Nil),
+        0x02 => Token::Event(Event::Boolean(false)),
+        0x03 => Token::Event(Event::Boolean(true)),
+        0x08 => Token::Binary(try!(read_length(reader, strict))),
+        0x09 => try!(read_string(reader, strict)),
+        0x0A => {
+            let size = try!(read_size(reader, strict));
+
+            try!(check_canonical(strict, !matches!(size, Size::U64(n) if n < 0b00001111)));
+
+            Token::Event(Event::StartArray(size))
+        }
+        0x0B => Token::Event(Event::StartStruct(try!(read_size(reader, strict)))),
+        0x0C => {
+            let size = try!(read_size(reader, strict));
+
+            try!(check_canonical(strict, !matches!(size, Size::U64(n) if n < 0b00001111)));
+
+            Token::Event(Event::StartMap(size))
+        }
+        0x0D => Token::Event(Event::StartOpenStruct(try!(read_size(reader, strict)))),
+        0x10 => Token::Event(Event::U8(try!(read_u8(reader)))),
+        0x11 => Token::Event(Event::U16(try!(read_u16(reader)))),
+        0x12 => Token::Event(Event::U32(try!(read_u32(reader)))),
+        0x13 => Token::Event(Event::U64(try!(read_u64(reader)))),
+        0x14 => Token::Event(Event::I8(try!(read_u8(reader)) as i8)),
+        0x15 => Token::Event(Event::I16(try!(read_u16(reader)) as i16)),
+        0x16 => Token::Event(Event::I32(try!(read_u32(reader)) as i32)),
+        0x17 => Token::Event(Event::I64(try!(read_u64(reader)) as i64)),
+        0x18 => Token::Fixnum(try!(read_length(reader, strict))),
+        0x1A => Token::Event(Event::F32(f32::from_bits(try!(read_u32(reader))))),
+        0x1B => Token::Event(Event::F64(f64::from_bits(try!(read_u64(reader))))),
+        x if x & 0b10000000 == 0b10000000 => Token::Dictionary(x as u64 & 0b01111111),
+        x if x & 0b11100000 == 0b01100000 => Token::String(x as u64 & 0b00011111),
+        x if x & 0b11110000 == 0b00100000 => {
+            try!(check_canonical(strict, x & 0b00001111 != 0b00001111));
+
+            Token::Event(Event::StartArray(Size::U64(x as u64 & 0b00001111)))
+        }
+        x if x & 0b11110000 == 0b00110000 => {
+            try!(check_canonical(strict, x & 0b00001111 != 0b00001111));
+
+            Token::Event(Event::StartMap(Size::U64(x as u64 & 0b00001111)))
+        }
+        _ => {
+            return Err(DecoderError::StreamError(ErrorCode::InvalidType));
+        }
+    };
+
+    if let Token::Event(ref event) = result {
+        try!(check_canonical(strict, canonical::is_canonical(event)));
+    }
+
+    return Ok(result);
+}
+
+pub fn parse<'b>(input: &mut &'b [u8], dictionary: &'b [&'b str], limits: &DecoderLimits) -> DecoderResult<Event<'b>> {
+    let token = match read_token(input, false) {
+        Ok(token) => token,
+        Err(DecoderError::StreamError(ErrorCode::UnexpectedEOF)) => {
+            return Err(DecoderError::StreamError(ErrorCode::NeedMoreData));
+        }
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    let result = match token {
+        Token::Event(event) => event,
+        Token::Binary(length) => Event::Binary(try!(take(input, length, limits))),
+        Token::Fixnum(length) => Event::Fixnum(try!(take(input, length, limits))),
+        Token::String(length) => Event::String(try!(read_string_data(input, length, limits))),
+        Token::Dictionary(index) => Event::String(try!(read_dictionary(dictionary, index)))
+    };
+
+    return Ok(result);
+}
+
+pub fn container(event: &Event) -> Option<(Size, usize, usize)> {
+    return match *event {
+        Event::StartArray(size) => Some((size, 1, 0)),
+        Event::StartStruct(size) => Some((size, 1, 1)),
+        Event::StartMap(size) => Some((size, 2, 0)),
+        Event::StartOpenStruct(size) => Some((size, 2, 1)),
+        _ => None
+    };
+}
+
+pub fn read_dictionary<'b>(dictionary: &'b [&'b str], index: u64) -> DecoderResult<&'b str> {
+    return match dictionary.get(index as usize) {
+        Some(s) => Ok(s),
+        None => Err(DecoderError::StreamError(ErrorCode::InvalidDictionaryIndex))
+    };
+}
+
+#[inline]
+fn check_canonical(strict: bool, canonical: bool) -> DecoderResult<()> {
+    if strict && !canonical {
+        return Err(DecoderError::StreamError(ErrorCode::NonCanonical));
+    }
+
+    return Ok(());
+}
+
+#[inline]
+fn read_string<R: io::Read + ?Sized>(reader: &mut R, strict: bool) -> DecoderResult<Token> {
+    let (index, minimum) = match try!(read_u8(reader)) {
+        0xF5 => (try!(read_u8(reader)) as u64, 0x80),
+        0xF6 => (try!(read_u16(reader)) as u64, 0x100),
+        0xF7 => (try!(read_u32(reader)) as u64, 0x10000),
+        0xF8 => (try!(read_u64(reader)), 0x100000000),
+        x => {
+            let length = try!(read_length_with_prefix(reader, x, strict));
+
+            try!(check_canonical(strict, length > 0b00011111));
+
+            return Ok(Token::String(length));
+        }
+    };
+
+    try!(check_canonical(strict, index >= minimum));
+
+    return Ok(Token::Dictionary(index));
+}
+
+#[inline]
+fn read_size<R: io::Read + ?Sized>(reader: &mut R, strict: bool) -> DecoderResult<Size> {
+    return match try!(read_u8(reader)) {
+        0xF0 => {
+            try!(check_canonical(strict, false));
+
+            Ok(Size::Streaming)
+        }
+        x => Ok(Size::U64(try!(read_length_with_prefix(reader, x, strict))))
+    };
+}
+
+#[inline]
+fn read_length<R: io::Read + ?Sized>(reader: &mut R, strict: bool) -> DecoderResult<u64> {
+    let prefix = try!(read_u8(reader));
+
+    return read_length_with_prefix(reader, prefix, strict);
+}
+
+#[inline]
+fn read_length_with_prefix<R: io::Read + ?Sized>(reader: &mut R, prefix: u8, strict: bool) -> DecoderResult<u64> {
+    let (result, minimum) = match prefix {
+        x if x < 0xEF => (x as u64, 0),
+        0xF1 => (try!(read_u8(reader)) as u64, 0xEF),
+        0xF2 => (try!(read_u16(reader)) as u64, 0x100),
+        0xF3 => (try!(read_u32(reader)) as u64, 0x10000),
+        0xF4 => (try!(read_u64(reader)), 0x100000000),
+        _ => {
+            return Err(DecoderError::StreamError(ErrorCode::InvalidLength));
+        }
+    };
+
+    try!(check_canonical(strict, result >= minimum));
+
+    return Ok(result);
+}
+
+#[inline]
+fn read_exact<R: io::Read + ?Sized>(reader: &mut R, buffer: &mut [u8]) -> DecoderResult<()> {
+    return match reader.read_exact(buffer) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(DecoderError::StreamError(ErrorCode::UnexpectedEOF)),
+        Err(e) => Err(DecoderError::IoError(e))
+    };
+}
+
+#[inline]
+fn read_u8<R: io::Read + ?Sized>(reader: &mut R) -> DecoderResult<u8> {
+    let mut buffer = [0; 1];
+
+    try!(read_exact(reader, &mut buffer));
+
+    return Ok(buffer[0]);
+}
+
+#[inline]
+fn read_u16<R: io::Read + ?Sized>(reader: &mut R) -> DecoderResult<u16> {
+    let mut buffer = [0; 2];
+
+    try!(read_exact(reader, &mut buffer));
+
+    return Ok(u16::from_le_bytes(buffer));
+}
+
+#[inline]
+fn read_u32<R: io::Read + ?Sized>(reader: &mut R) -> DecoderResult<u32> {
+    let mut buffer = [0; 4];
+
+    try!(read_exact(reader, &mut buffer));
+
+    return Ok(u32::from_le_bytes(buffer));
+}
+
+#[inline]
+fn read_u64<R: io::Read + ?Sized>(reader: &mut R) -> DecoderResult<u64> {
+    let mut buffer = [0; 8];
+
+    try!(read_exact(reader, &mut buffer));
+
+    return Ok(u64::from_le_bytes(buffer));
+}
+
+#[inline]
+fn take<'b>(input: &mut &'b [u8], length: u64, limits: &DecoderLimits) -> DecoderResult<&'b [u8]> {
+    try!(limits.check_length(length));
+
+    if (input.len() as u64) < length {
+        return Err(DecoderError::StreamError(ErrorCode::NeedMoreData));
+    }
+
+    let (head, tail) = input.split_at(length as usize);
+
+    *input = tail;
+
+    return Ok(head);
+}
+
+#[inline]
+fn read_string_data<'b>(input: &mut &'b [u8], length: u64, limits: &DecoderLimits) -> DecoderResult<&'b str> {
+    let buffer = try!(take(input, length, limits));
+
+    return match str::from_utf8(buffer) {
+        Ok(s) => Ok(s),
+        Err(_) => Err(DecoderError::StreamError(ErrorCode::InvalidUTF8))
+    };
+}
@@ -0,0 +1,420 @@
+use std::ops;
+
+use super::{Event, Size};
+
+use decoder::Decoder;
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use encoder::Encoder;
+use encoder_error::EncoderResult;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    U8(u8), U16(u16), U32(u32), U64(u64),
+    I8(i8), I16(i16), I32(i32), I64(i64), Fixnum(Vec<u8>),
+    F32(f32), F64(f64),
+    Binary(Vec<u8>),
+    String(String),
+    Array(Vec<Value>),
+    Struct { name: Box<Value>, fields: Vec<Value> },
+    Map(Vec<(Value, Value)>),
+    OpenStruct { name: Box<Value>, fields: Vec<(Value, Value)> }
+}
+
+impl Value {
+    pub fn from_events(decoder: &mut Decoder) -> DecoderResult<Value> {
+        return match try!(decoder.read()) {
+            Some(event) => Value::from_event(decoder, event),
+            None => Err(DecoderError::StreamError(ErrorCode::EndOfStream))
+        };
+    }
+
+    fn from_event(decoder: &mut Decoder, event: Event) -> DecoderResult<Value> {
+        let result = match event {
+            Event::Nil => Value::Nil,
+            Event::Boolean(v) => Value::Boolean(v),
+            Event::U8(v) => Value::U8(v),
+            Event::U16(v) => Value::U16(v),
+            Event::U32(v) => Value::U32(v),
+            Event::U64(v) => Value::U64(v),
+            Event::I8(v) => Value::I8(v),
+            Event::I16(v) => Value::I16(v),
+            Event::I32(v) => Value::I32(v),
+            Event::I64(v) => Value::I64(v),
+            Event::Fixnum(v) => Value::Fixnum(v.to_vec()),
+            Event::F32(v) => Value::F32(v),
+            Event::F64(v) => Value::F64(v),
+            Event::Binary(v) => Value::Binary(v.to_vec()),
+            Event::String(v) => Value::String(v.to_string()),
+            Event::StartArray(_) => {
+                let mut elements = Vec::new();
+
+                while let Some(element) = try!(Value::read_element(decoder)) {
+                    elements.push(element);
+                }
+
+                Value::Array(elements)
+            }
+            Event::StartStruct(_) => {
+                let name = try!(Value::read_required_element(decoder));
+                let mut fields = Vec::new();
+
+                while let Some(field) = try!(Value::read_element(decoder)) {
+                    fields.push(field);
+                }
+
+                Value::Struct { name: Box::new(name), fields: fields }
+            }
+            Event::StartMap(_) => {
+                Value::Map(try!(Value::read_pairs(decoder)))
+            }
+            Event::StartOpenStruct(_) => {
+                let name = try!(Value::read_required_element(decoder));
+                let fields = try!(Value::read_pairs(decoder));
+
+                Value::OpenStruct { name: Box::new(name), fields: fields }
+            }
+            Event::End => {
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+            }
+        };
+
+        return Ok(result);
+    }
+
+    fn read_element(decoder: &mut Decoder) -> DecoderResult<Option<Value>> {
+        return match try!(decoder.read()) {
+            Some(Event::End) => Ok(None),
+            Some(event) => Value::from_event(decoder, event).map(Some),
+            None => Err(DecoderError::StreamError(ErrorCode::EndOfStream))
+        };
+    }
+
+    fn read_required_element(decoder: &mut Decoder) -> DecoderResult<Value> {
+        return match try!(Value::read_element(decoder)) {
+            Some(value) => Ok(value),
+            None => Err(DecoderError::StreamError(ErrorCode::InvalidEnd))
+        };
+    }
+
+    fn read_pairs(decoder: &mut Decoder) -> DecoderResult<Vec<(Value, Value)>> {
+        let mut pairs = Vec::new();
+
+        while let Some(key) = try!(Value::read_element(decoder)) {
+            let value = try!(Value::read_required_element(decoder));
+
+            pairs.push((key, value));
+        }
+
+        return Ok(pairs);
+    }
+
+    pub fn write_to(&self, encoder: &mut Encoder) -> EncoderResult<()> {
+        match *self {
+            Value::Nil => try!(encoder.write(&Event::Nil)),
+            Value::Boolean(v) => try!(encoder.write(&Event::Boolean(v))),
+            Value::U8(v) => try!(encoder.write(&Event::U8(v))),
+            Value::U16(v) => try!(encoder.write(&Event::U16(v))),
+            Value::U32(v) => try!(encoder.write(&Event::U32(v))),
+            Value::U64(v) => try!(encoder.write(&Event::U64(v))),
+            Value::I8(v) => try!(encoder.write(&Event::I8(v))),
+            Value::I16(v) => try!(encoder.write(&Event::I16(v))),
+            Value::I32(v) => try!(encoder.write(&Event::I32(v))),
+            Value::I64(v) => try!(encoder.write(&Event::I64(v))),
+            Value::Fixnum(ref v) => try!(encoder.write(&Event::Fixnum(v))),
+            Value::F32(v) => try!(encoder.write(&Event::F32(v))),
+            Value::F64(v) => try!(encoder.write(&Event::F64(v))),
+            Value::Binary(ref v) => try!(encoder.write(&Event::Binary(v))),
+            Value::String(ref v) => try!(encoder.write(&Event::String(v))),
+            Value::Array(ref elements) => {
+                try!(encoder.write(&Event::StartArray(Size::U64(elements.len() as u64))));
+
+                for element in elements {
+                    try!(element.write_to(encoder));
+                }
+
+                try!(encoder.write(&Event::End));
+            }
+            Value::Struct { ref name, ref fields } => {
+                try!(encoder.write(&Event::StartStruct(Size::U64(fields.len() as u64))));
+                try!(name.write_to(encoder));
+
+                for field in fields {
+                    try!(field.write_to(encoder));
+                }
+
+                try!(encoder.write(&Event::End));
+            }
+            Value::Map(ref pairs) => {
+                try!(encoder.write(&Event::StartMap(Size::U64(pairs.len() as u64))));
+                try!(Value::write_pairs(encoder, pairs));
+                try!(encoder.write(&Event::End));
+            }
+            Value::OpenStruct { ref name, ref fields } => {
+                try!(encoder.write(&Event::StartOpenStruct(Size::U64(fields.len() as u64))));
+                try!(name.write_to(encoder));
+                try!(Value::write_pairs(encoder, fields));
+                try!(encoder.write(&Event::End));
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn write_pairs(encoder: &mut Encoder, pairs: &[(Value, Value)]) -> EncoderResult<()> {
+        for &(ref key, ref value) in pairs {
+            try!(key.write_to(encoder));
+            try!(value.write_to(encoder));
+        }
+
+        return Ok(());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let pairs = match *self {
+            Value::Map(ref pairs) => pairs,
+            Value::OpenStruct { ref fields, .. } => fields,
+            _ => return None
+        };
+
+        return pairs.iter().find(|&&(ref k, _)| k.as_str() == Some(key)).map(|&(_, ref v)| v);
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&Value> {
+        return match *self {
+            Value::Array(ref elements) => elements.get(index),
+            Value::Struct { ref fields, .. } => fields.get(index),
+            _ => None
+        };
+    }
+
+    pub fn is_nil(&self) -> bool {
+        return *self == Value::Nil;
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        return match *self {
+            Value::Boolean(v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        return match *self {
+            Value::U8(v) => Some(v as u64),
+            Value::U16(v) => Some(v as u64),
+            Value::U32(v) => Some(v as u64),
+            Value::U64(v) => Some(v),
+            Value::I8(v) if v >= 0 => Some(v as u64),
+            Value::I16(v) if v >= 0 => Some(v as u64),
+            Value::I32(v) if v >= 0 => Some(v as u64),
+            Value::I64(v) if v >= 0 => Some(v as u64),
+            _ => None
+        };
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        return match *self {
+            Value::U8(v) => Some(v as i64),
+            Value::U16(v) => Some(v as i64),
+            Value::U32(v) => Some(v as i64),
+            Value::U64(v) if v <= i64::MAX as u64 => Some(v as i64),
+            Value::I8(v) => Some(v as i64),
+            Value::I16(v) => Some(v as i64),
+            Value::I32(v) => Some(v as i64),
+            Value::I64(v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        return match *self {
+            Value::F32(v) => Some(v as f64),
+            Value::F64(v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_fixnum(&self) -> Option<&[u8]> {
+        return match *self {
+            Value::Fixnum(ref v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_binary(&self) -> Option<&[u8]> {
+        return match *self {
+            Value::Binary(ref v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        return match *self {
+            Value::String(ref v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        return match *self {
+            Value::Array(ref v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_struct(&self) -> Option<(&Value, &[Value])> {
+        return match *self {
+            Value::Struct { ref name, ref fields } => Some((name, fields)),
+            _ => None
+        };
+    }
+
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        return match *self {
+            Value::Map(ref v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_open_struct(&self) -> Option<(&Value, &[(Value, Value)])> {
+        return match *self {
+            Value::OpenStruct { ref name, ref fields } => Some((name, fields)),
+            _ => None
+        };
+    }
+}
+
+impl ops::Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        return match self.get_index(index) {
+            Some(value) => value,
+            None => panic!("No element at index {} in {:?}", index, self)
+        };
+    }
+}
+
+impl<'a> ops::Index<&'a str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &'a str) -> &Value {
+        return match self.get(key) {
+            Some(value) => value,
+            None => panic!("No entry for key {:?} in {:?}", key, self)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use ::{Decoder, Encoder};
+
+    use decoder_error::ErrorCode;
+
+    use super::Value;
+
+    fn person() -> Value {
+        return Value::OpenStruct {
+            name: Box::new(Value::String("person".to_string())),
+            fields: vec![
+                (Value::String("name".to_string()), Value::String("🍪".to_string())),
+                (Value::String("age".to_string()), Value::U8(7)),
+                (Value::String("tags".to_string()), Value::Array(vec![Value::Nil, Value::Boolean(true), Value::Binary(vec![0x01, 0x02])])),
+                (Value::String("point".to_string()), Value::Struct {
+                    name: Box::new(Value::String("point".to_string())),
+                    fields: vec![Value::I16(-1), Value::F64(2.5)]
+                }),
+                (Value::String("extra".to_string()), Value::Map(vec![(Value::U32(1), Value::Fixnum(vec![0x80, 0x00]))]))
+            ]
+        };
+    }
+
+    #[test]
+    fn transcodes_value() {
+        let dictionary = vec!["person", "name"];
+        let value = person();
+
+        let mut cursor = io::Cursor::new(Vec::new());
+
+        {
+            let mut encoder = Encoder::new(&mut cursor, &dictionary[..]);
+
+            value.write_to(&mut encoder).unwrap();
+        }
+
+        let stream = cursor.into_inner();
+
+        let mut cursor = io::Cursor::new(&*stream);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(Value::from_events(&mut decoder), Ok(value));
+        assert_eq!(decoder.read(), Ok(None));
+    }
+
+    #[test]
+    fn decodes_streaming_containers() {
+        let data = vec![0x0A, 0xF0, 0x01, 0x0C, 0xF0, 0x10, 0x01, 0x02, 0x00, 0x00];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        let expected = Value::Array(vec![Value::Nil, Value::Map(vec![(Value::U8(1), Value::Boolean(false))])]);
+
+        assert_eq!(Value::from_events(&mut decoder), Ok(expected));
+    }
+
+    #[test]
+    fn reports_empty_stream() {
+        let data: Vec<u8> = vec![0x01];
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(Value::from_events(&mut decoder), Ok(Value::Nil));
+        assert_eq!(Value::from_events(&mut decoder).unwrap_err().code(), Some(&ErrorCode::EndOfStream));
+    }
+
+    #[test]
+    fn limits_nesting_by_default() {
+        let mut data = vec![0x21; 100000];
+        data.push(0x01);
+
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(Value::from_events(&mut decoder).unwrap_err().code(), Some(&ErrorCode::DepthLimitExceeded));
+    }
+
+    #[test]
+    fn indexes_by_key_and_position() {
+        let value = person();
+
+        assert_eq!(value["name"].as_str(), Some("🍪"));
+        assert_eq!(value["age"].as_u64(), Some(7));
+        assert_eq!(value["age"].as_i64(), Some(7));
+        assert!(value["tags"][0].is_nil());
+        assert_eq!(value["tags"][1].as_bool(), Some(true));
+        assert_eq!(value["tags"][2].as_binary(), Some(&[0x01, 0x02][..]));
+        assert_eq!(value["point"][0].as_i64(), Some(-1));
+        assert_eq!(value["point"][0].as_u64(), None);
+        assert_eq!(value["point"][1].as_f64(), Some(2.5));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(value["tags"].get_index(3), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn panics_on_missing_key() {
+        let _ = &person()["missing"];
+    }
+}
@@ -0,0 +1,260 @@
+use std::ops;
+
+use super::Event;
+
+use decoder::Decoder;
+use decoder_error::{ErrorCode, DecoderError, DecoderResult};
+use value::Value;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ValueRef<'a> {
+    Nil,
+    Boolean(bool),
+    U8(u8), U16(u16), U32(u32), U64(u64),
+    I8(i8), I16(i16), I32(i32), I64(i64), Fixnum(&'a [u8]),
+    F32(f32), F64(f64),
+    Binary(&'a [u8]),
+    String(&'a str),
+    Array(Vec<ValueRef<'a>>),
+    Struct { name: Box<ValueRef<'a>>, fields: Vec<ValueRef<'a>> },
+    Map(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+    OpenStruct { name: Box<ValueRef<'a>>, fields: Vec<(ValueRef<'a>, ValueRef<'a>)> }
+}
+
+impl<'a> ValueRef<'a> {
+    pub fn from_events(decoder: &mut Decoder<'a>) -> DecoderResult<ValueRef<'a>> {
+        return match try!(decoder.read()) {
+            Some(event) => ValueRef::from_event(decoder, event),
+            None => Err(DecoderError::StreamError(ErrorCode::EndOfStream))
+        };
+    }
+
+    fn from_event(decoder: &mut Decoder<'a>, event: Event<'a>) -> DecoderResult<ValueRef<'a>> {
+        let result = match event {
+            Event::Nil => ValueRef::Nil,
+            Event::Boolean(v) => ValueRef::Boolean(v),
+            Event::U8(v) => ValueRef::U8(v),
+            Event::U16(v) => ValueRef::U16(v),
+            Event::U32(v) => ValueRef::U32(v),
+            Event::U64(v) => ValueRef::U64(v),
+            Event::I8(v) => ValueRef::I8(v),
+            Event::I16(v) => ValueRef::I16(v),
+            Event::I32(v) => ValueRef::I32(v),
+            Event::I64(v) => ValueRef::I64(v),
+            Event::Fixnum(v) => ValueRef::Fixnum(v),
+            Event::F32(v) => ValueRef::F32(v),
+            Event::F64(v) => ValueRef::F64(v),
+            Event::Binary(v) => ValueRef::Binary(v),
+            Event::String(v) => ValueRef::String(v),
+            Event::StartArray(_) => {
+                let mut elements = Vec::new();
+
+                while let Some(element) = try!(ValueRef::read_element(decoder)) {
+                    elements.push(element);
+                }
+
+                ValueRef::Array(elements)
+            }
+            Event::StartStruct(_) => {
+                let name = try!(ValueRef::read_required_element(decoder));
+                let mut fields = Vec::new();
+
+                while let Some(field) = try!(ValueRef::read_element(decoder)) {
+                    fields.push(field);
+                }
+
+                ValueRef::Struct { name: Box::new(name), fields: fields }
+            }
+            Event::StartMap(_) => {
+                ValueRef::Map(try!(ValueRef::read_pairs(decoder)))
+            }
+            Event::StartOpenStruct(_) => {
+                let name = try!(ValueRef::read_required_element(decoder));
+                let fields = try!(ValueRef::read_pairs(decoder));
+
+                ValueRef::OpenStruct { name: Box::new(name), fields: fields }
+            }
+            Event::End => {
+                return Err(DecoderError::StreamError(ErrorCode::InvalidEnd));
+            }
+        };
+
+        return Ok(result);
+    }
+
+    fn read_element(decoder: &mut Decoder<'a>) -> DecoderResult<Option<ValueRef<'a>>> {
+        return match try!(decoder.read()) {
+            Some(Event::End) => Ok(None),
+            Some(event) => ValueRef::from_event(decoder, event).map(Some),
+            None => Err(DecoderError::StreamError(ErrorCode::EndOfStream))
+        };
+    }
+
+    fn read_required_element(decoder: &mut Decoder<'a>) -> DecoderResult<ValueRef<'a>> {
+        return match try!(ValueRef::read_element(decoder)) {
+            Some(value) => Ok(value),
+            None => Err(DecoderError::StreamError(ErrorCode::InvalidEnd))
+        };
+    }
+
+    fn read_pairs(decoder: &mut Decoder<'a>) -> DecoderResult<Vec<(ValueRef<'a>, ValueRef<'a>)>> {
+        let mut pairs = Vec::new();
+
+        while let Some(key) = try!(ValueRef::read_element(decoder)) {
+            let value = try!(ValueRef::read_required_element(decoder));
+
+            pairs.push((key, value));
+        }
+
+        return Ok(pairs);
+    }
+
+    pub fn to_owned(&self) -> Value {
+        return match *self {
+            ValueRef::Nil => Value::Nil,
+            ValueRef::Boolean(v) => Value::Boolean(v),
+            ValueRef::U8(v) => Value::U8(v),
+            ValueRef::U16(v) => Value::U16(v),
+            ValueRef::U32(v) => Value::U32(v),
+            ValueRef::U64(v) => Value::U64(v),
+            ValueRef::I8(v) => Value::I8(v),
+            ValueRef::I16(v) => Value::I16(v),
+            ValueRef::I32(v) => Value::I32(v),
+            ValueRef::I64(v) => Value::I64(v),
+            ValueRef::Fixnum(v) => Value::Fixnum(v.to_vec()),
+            ValueRef::F32(v) => Value::F32(v),
+            ValueRef::F64(v) => Value::F64(v),
+            ValueRef::Binary(v) => Value::Binary(v.to_vec()),
+            ValueRef::String(v) => Value::String(v.to_string()),
+            ValueRef::Array(ref elements) => Value::Array(elements.iter().map(|e| e.to_owned()).collect()),
+            ValueRef::Struct { ref name, ref fields } => Value::Struct {
+                name: Box::new(ValueRef::to_owned(name)),
+                fields: fields.iter().map(|f| f.to_owned()).collect()
+            },
+            ValueRef::Map(ref pairs) => Value::Map(ValueRef::pairs_to_owned(pairs)),
+            ValueRef::OpenStruct { ref name, ref fields } => Value::OpenStruct {
+                name: Box::new(ValueRef::to_owned(name)),
+                fields: ValueRef::pairs_to_owned(fields)
+            }
+        };
+    }
+
+    fn pairs_to_owned(pairs: &[(ValueRef<'a>, ValueRef<'a>)]) -> Vec<(Value, Value)> {
+        return pairs.iter().map(|&(ref k, ref v)| (k.to_owned(), v.to_owned())).collect();
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ValueRef<'a>> {
+        let pairs = match *self {
+            ValueRef::Map(ref pairs) => pairs,
+            ValueRef::OpenStruct { ref fields, .. } => fields,
+            _ => return None
+        };
+
+        return pairs.iter().find(|&&(ref k, _)| k.as_str() == Some(key)).map(|&(_, ref v)| v);
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&ValueRef<'a>> {
+        return match *self {
+            ValueRef::Array(ref elements) => elements.get(index),
+            ValueRef::Struct { ref fields, .. } => fields.get(index),
+            _ => None
+        };
+    }
+
+    pub fn as_binary(&self) -> Option<&'a [u8]> {
+        return match *self {
+            ValueRef::Binary(v) => Some(v),
+            _ => None
+        };
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        return match *self {
+            ValueRef::String(v) => Some(v),
+            _ => None
+        };
+    }
+}
+
+impl<'a> ops::Index<usize> for ValueRef<'a> {
+    type Output = ValueRef<'a>;
+
+    fn index(&self, index: usize) -> &ValueRef<'a> {
+        return match self.get_index(index) {
+            Some(value) => value,
+            None => panic!("No element at index {} in {:?}", index, self)
+        };
+    }
+}
+
+impl<'a, 'b> ops::Index<&'b str> for ValueRef<'a> {
+    type Output = ValueRef<'a>;
+
+    fn index(&self, key: &'b str) -> &ValueRef<'a> {
+        return match self.get(key) {
+            Some(value) => value,
+            None => panic!("No entry for key {:?} in {:?}", key, self)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use ::{Decoder, Value};
+
+    use decoder_error::ErrorCode;
+
+    use super::ValueRef;
+
+    #[test]
+    fn borrows_from_input() {
+        let data = vec![0x0D, 0x02, 0x80, 0x81, 0x64, 0xF0, 0x9F, 0x8D, 0xAA, 0x62, 0x69, 0x64, 0x08, 0x02, 0x01, 0x02];
+        let dictionary = vec!["person", "name"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        let value = ValueRef::from_events(&mut decoder).unwrap();
+
+        let name = value["name"].as_str().unwrap();
+        let id = value["id"].as_binary().unwrap();
+
+        assert_eq!(name, "🍪");
+        assert_eq!(id, &[0x01, 0x02]);
+        assert_eq!(name.as_ptr(), data[5..].as_ptr());
+        assert_eq!(id.as_ptr(), data[14..].as_ptr());
+    }
+
+    #[test]
+    fn converts_to_owned() {
+        let data = vec![0x22, 0x0B, 0x01, 0x80, 0x10, 0x05, 0x61, 0x78];
+        let dictionary = vec!["point"];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        let value = ValueRef::from_events(&mut decoder).unwrap();
+
+        let expected = Value::Array(vec![
+            Value::Struct { name: Box::new(Value::String("point".to_string())), fields: vec![Value::U8(5)] },
+            Value::String("x".to_string())
+        ]);
+
+        assert_eq!(value.to_owned(), expected);
+    }
+
+    #[test]
+    fn limits_nesting_by_default() {
+        let mut data = vec![0x21; 100000];
+        data.push(0x01);
+
+        let dictionary: Vec<&'static str> = vec![];
+        let mut cursor = io::Cursor::new(&*data);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(ValueRef::from_events(&mut decoder).unwrap_err().code(), Some(&ErrorCode::DepthLimitExceeded));
+    }
+}
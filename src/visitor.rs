@@ -0,0 +1,161 @@
+use super::Size;
+
+use decoder_error::DecoderResult;
+
+pub trait Visitor<'a> {
+    fn visit_nil(&mut self) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_bool(&mut self, _value: bool) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_u8(&mut self, _value: u8) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_u16(&mut self, _value: u16) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_u32(&mut self, _value: u32) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_u64(&mut self, _value: u64) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_i8(&mut self, _value: i8) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_i16(&mut self, _value: i16) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_i32(&mut self, _value: i32) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_i64(&mut self, _value: i64) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_fixnum(&mut self, _value: &'a [u8]) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_f32(&mut self, _value: f32) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_f64(&mut self, _value: f64) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_binary(&mut self, _value: &'a [u8]) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn visit_str(&mut self, _value: &'a str) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn begin_array(&mut self, _size: Size) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn begin_struct(&mut self, _name: &'a str, _size: Size) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn begin_map(&mut self, _size: Size) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn begin_open_struct(&mut self, _name: &'a str, _size: Size) -> DecoderResult<()> {
+        return Ok(());
+    }
+
+    fn end(&mut self) -> DecoderResult<()> {
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use ::Size;
+
+    use decoder::Decoder;
+    use decoder_error::{DecoderError, DecoderResult, ErrorCode};
+
+    use super::Visitor;
+
+    #[derive(Default)]
+    struct Collector {
+        calls: Vec<String>
+    }
+
+    impl<'a> Visitor<'a> for Collector {
+        fn visit_u32(&mut self, value: u32) -> DecoderResult<()> {
+            self.calls.push(format!("u32 {}", value));
+            return Ok(());
+        }
+
+        fn visit_str(&mut self, value: &'a str) -> DecoderResult<()> {
+            self.calls.push(format!("str {}", value));
+            return Ok(());
+        }
+
+        fn begin_struct(&mut self, name: &'a str, size: Size) -> DecoderResult<()> {
+            self.calls.push(format!("struct {} {:?}", name, size));
+            return Ok(());
+        }
+
+        fn begin_map(&mut self, size: Size) -> DecoderResult<()> {
+            self.calls.push(format!("map {:?}", size));
+            return Ok(());
+        }
+
+        fn end(&mut self) -> DecoderResult<()> {
+            self.calls.push("end".to_string());
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn drives_visitor() {
+        let data = [0x0B, 0x02, 0x80, 0x12, 0x07, 0x00, 0x00, 0x00, 0x31, 0x61, 0x61, 0x01];
+        let dictionary = ["user"];
+        let mut cursor = io::Cursor::new(&data[..]);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+        let mut collector = Collector::default();
+
+        assert_eq!(decoder.drive(&mut collector), Ok(()));
+        assert_eq!(collector.calls, vec!["struct user U64(2)", "u32 7", "map U64(1)", "str a", "end", "end"]);
+    }
+
+    struct Rejector;
+
+    impl<'a> Visitor<'a> for Rejector {
+        fn visit_str(&mut self, _value: &'a str) -> DecoderResult<()> {
+            return Err(DecoderError::StreamError(ErrorCode::InvalidType));
+        }
+    }
+
+    #[test]
+    fn stops_on_visitor_error() {
+        let data = [0x22, 0x10, 0x07, 0x61, 0x61];
+        let dictionary: [&str; 0] = [];
+        let mut cursor = io::Cursor::new(&data[..]);
+
+        let mut decoder = Decoder::new(&mut cursor, &dictionary[..]);
+
+        assert_eq!(decoder.drive(&mut Rejector), Err(DecoderError::StreamError(ErrorCode::InvalidType)));
+    }
+}